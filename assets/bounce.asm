@@ -0,0 +1,36 @@
+; Bounce, a ball bouncing off the edges of the screen
+; cargo run --release -- assets/bounce.asm
+
+        LD V0, 10           ; ball position
+        LD V1, 5
+        LD V2, 1            ; horizontal and vertical steps, 0xFF moving back
+        LD V3, 1
+        LD I, ball
+        DRW V0, V1, 4
+
+loop:   LD V4, 2            ; 30 moves a second
+        LD DT, V4
+wait:   LD V4, DT
+        SE V4, 0
+        JP wait
+
+        LD V5, V0           ; next position
+        ADD V5, V2
+        LD V6, V1
+        ADD V6, V3
+        SNE V5, 0           ; turn around at the edges, the ball is 4x4
+        LD V2, 1
+        SNE V5, 60
+        LD V2, 0xFF
+        SNE V6, 0
+        LD V3, 1
+        SNE V6, 28
+        LD V3, 0xFF
+
+        DRW V0, V1, 4       ; erase and draw straight after to keep flicker down
+        DRW V5, V6, 4
+        LD V0, V5
+        LD V1, V6
+        JP loop
+
+ball:   DB 0x60, 0xF0, 0xF0, 0x60
@@ -0,0 +1,602 @@
+pub mod assembly {
+    use crate::chip8::processor::chip::{MEMORY_SIZE, PROGRAM_START};
+    use std::{collections::HashMap, error::Error, fmt};
+
+    /// A source error, at a 1 based line and column
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct AsmError {
+        pub line: usize,
+        pub column: usize,
+        pub message: String,
+    }
+
+    impl fmt::Display for AsmError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}:{}: {}", self.line, self.column, self.message)
+        }
+    }
+
+    impl Error for AsmError {}
+
+    // A piece of a line between separators, with the column it starts at
+    struct Token<'a> {
+        text: &'a str,
+        column: usize,
+    }
+
+    // A line that emits bytes, kept from the first pass along with its address
+    struct Statement<'a> {
+        line: usize,
+        address: usize,
+        tokens: Vec<Token<'a>>,
+    }
+
+    enum Operand {
+        V(u16),
+        Range(u16, u16),
+        I,
+        Memory,
+        Dt,
+        St,
+        K,
+        F,
+        Hf,
+        B,
+        R,
+        Long,
+        Value(i64, usize),
+    }
+
+    /// Assembles a program loaded at 0x200, see `assemble_at`
+    pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+        assemble_at(source, PROGRAM_START)
+    }
+
+    /// Assembles `source` into a ROM loaded at `origin`.
+    ///
+    /// Each line holds an optional `label:` followed by an instruction or a directive, and a
+    /// `;` starts a comment. Instructions use the classic mnemonics listed by the disassembler,
+    /// such as `LD V1, 0x0A`, `DRW V0, V1, 5` or `LD [I], V0-V3`, case insensitive, with
+    /// operands separated by commas. Values are decimal, `0x` hex or `0b` binary numbers,
+    /// labels and constants, added and subtracted with `+` and `-`.
+    ///
+    /// Directives
+    ///
+    /// ```text
+    /// :const NAME VALUE    defines a constant
+    /// :org ADDRESS         continues assembling at ADDRESS
+    /// :byte VALUE ...      emits bytes, also DB
+    /// :word VALUE ...      emits big endian words, also DW
+    /// :sprite ROW ...      emits a sprite row by row, X or # for set pixels and . for clear,
+    ///                      rows up to 8 pixels wide take a byte and up to 16 take two
+    /// ```
+    pub fn assemble_at(source: &str, origin: usize) -> Result<Vec<u8>, AsmError> {
+        let mut symbols = HashMap::new();
+        let mut statements = Vec::new();
+        let mut address = origin;
+
+        // First pass, placing every line and defining labels and constants
+        for (number, text) in source.lines().enumerate() {
+            let line = number + 1;
+            let mut tokens = tokenize(text);
+
+            if let Some(label) = tokens.first().filter(|t| is_label(t.text)) {
+                let name = label.text.trim_end_matches(':');
+                define(&mut symbols, name, address as i64, line, label.column)?;
+                tokens.remove(0);
+            }
+
+            let directive = match tokens.first() {
+                Some(token) => token.text.to_ascii_lowercase(),
+                None => continue,
+            };
+            let size = match directive.as_str() {
+                ":const" => {
+                    let (name, value) = match tokens.as_slice() {
+                        [_, name, value] => (name, evaluate(value, &symbols, line)?),
+                        _ => {
+                            let message = ":const takes a name and a value";
+                            return Err(error(line, tokens[0].column, message));
+                        }
+                    };
+                    define(&mut symbols, name.text, value, line, name.column)?;
+                    continue;
+                }
+                ":org" => {
+                    let value = match tokens.as_slice() {
+                        [_, value] => value,
+                        _ => return Err(error(line, tokens[0].column, ":org takes an address")),
+                    };
+                    let target = evaluate(value, &symbols, line)?;
+                    if target < origin as i64 || target >= MEMORY_SIZE as i64 {
+                        return Err(error(
+                            line,
+                            value.column,
+                            &format!("Address {:#X} is outside of the program", target),
+                        ));
+                    }
+                    address = target as usize;
+                    continue;
+                }
+                ":byte" | "db" => tokens.len() - 1,
+                ":word" | "dw" => (tokens.len() - 1) * 2,
+                ":sprite" => tokens[1..]
+                    .iter()
+                    .map(|row| if row.text.len() > 8 { 2 } else { 1 })
+                    .sum(),
+                "ld" if tokens.iter().any(|t| t.text.eq_ignore_ascii_case("long")) => 4,
+                _ => 2,
+            };
+
+            statements.push(Statement {
+                line,
+                address,
+                tokens,
+            });
+            address += size;
+            if address > MEMORY_SIZE {
+                return Err(error(line, 1, "The program does not fit in memory"));
+            }
+        }
+
+        // Second pass, encoding every line now that all labels are known
+        let mut rom = Vec::new();
+        for statement in &statements {
+            let bytes = encode(statement, &symbols)?;
+            let start = statement.address - origin;
+            if rom.len() < start + bytes.len() {
+                rom.resize(start + bytes.len(), 0);
+            }
+            rom[start..start + bytes.len()].copy_from_slice(&bytes);
+        }
+
+        Ok(rom)
+    }
+
+    fn error(line: usize, column: usize, message: &str) -> AsmError {
+        AsmError {
+            line,
+            column,
+            message: message.to_string(),
+        }
+    }
+
+    // Splits a line on whitespace and commas, up to a comment
+    fn tokenize(line: &str) -> Vec<Token<'_>> {
+        let code = line.split(';').next().unwrap_or_default();
+        let mut tokens = Vec::new();
+        let mut start = None;
+
+        for (i, c) in code.char_indices().chain([(code.len(), ' ')]) {
+            let separator = c.is_whitespace() || c == ',';
+            match start {
+                Some(s) if separator => {
+                    tokens.push(Token {
+                        text: &code[s..i],
+                        column: s + 1,
+                    });
+                    start = None;
+                }
+                None if !separator => start = Some(i),
+                _ => {}
+            }
+        }
+
+        tokens
+    }
+
+    fn is_identifier(name: &str) -> bool {
+        let mut chars = name.chars();
+        chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    fn is_label(text: &str) -> bool {
+        text.strip_suffix(':').is_some_and(is_identifier)
+    }
+
+    fn define(
+        symbols: &mut HashMap<String, i64>,
+        name: &str,
+        value: i64,
+        line: usize,
+        column: usize,
+    ) -> Result<(), AsmError> {
+        if !is_identifier(name) {
+            return Err(error(line, column, &format!("Invalid name \"{}\"", name)));
+        }
+        if symbols.insert(name.to_string(), value).is_some() {
+            return Err(error(
+                line,
+                column,
+                &format!("\"{}\" is already defined", name),
+            ));
+        }
+        Ok(())
+    }
+
+    // Evaluates numbers, labels and constants joined by + and -
+    fn evaluate(
+        token: &Token,
+        symbols: &HashMap<String, i64>,
+        line: usize,
+    ) -> Result<i64, AsmError> {
+        let mut rest = token.text;
+        let mut column = token.column;
+        let mut sign = 1;
+        let mut total: i64 = 0;
+
+        if let Some(negated) = rest.strip_prefix('-') {
+            rest = negated;
+            column += 1;
+            sign = -1;
+        }
+
+        loop {
+            let end = rest.find(['+', '-']).unwrap_or(rest.len());
+            let term = &rest[..end];
+            let value = if let Some(hex) = term.strip_prefix("0x").or(term.strip_prefix("0X")) {
+                i64::from_str_radix(hex, 16).ok()
+            } else if let Some(binary) = term.strip_prefix("0b").or(term.strip_prefix("0B")) {
+                i64::from_str_radix(binary, 2).ok()
+            } else if term.starts_with(|c: char| c.is_ascii_digit()) {
+                term.parse().ok()
+            } else {
+                symbols.get(term).copied()
+            };
+
+            let value = value.ok_or_else(|| {
+                if is_identifier(term) {
+                    let message = format!("Unknown label or constant \"{}\"", term);
+                    error(line, column, &message)
+                } else {
+                    let message = format!("Invalid value \"{}\"", token.text);
+                    error(line, token.column, &message)
+                }
+            })?;
+            total = value
+                .checked_mul(sign)
+                .and_then(|value| total.checked_add(value))
+                .ok_or_else(|| {
+                    let message = format!("Value \"{}\" is out of range", token.text);
+                    error(line, token.column, &message)
+                })?;
+
+            if end == rest.len() {
+                return Ok(total);
+            }
+            sign = if rest[end..].starts_with('-') { -1 } else { 1 };
+            column += end + 1;
+            rest = &rest[end + 1..];
+        }
+    }
+
+    fn register(name: &str) -> Option<u16> {
+        let digit = name.strip_prefix(['V', 'v'])?;
+        (digit.len() == 1)
+            .then(|| u16::from_str_radix(digit, 16).ok())
+            .flatten()
+    }
+
+    fn operand(
+        token: &Token,
+        symbols: &HashMap<String, i64>,
+        line: usize,
+    ) -> Result<Operand, AsmError> {
+        let operand = match token.text.to_ascii_uppercase().as_str() {
+            "I" => Operand::I,
+            "[I]" => Operand::Memory,
+            "DT" => Operand::Dt,
+            "ST" => Operand::St,
+            "K" => Operand::K,
+            "F" => Operand::F,
+            "HF" => Operand::Hf,
+            "B" => Operand::B,
+            "R" => Operand::R,
+            "LONG" => Operand::Long,
+            text => match (register(text), text.split_once('-')) {
+                (Some(x), _) => Operand::V(x),
+                (None, Some((x, y))) if register(x).is_some() && register(y).is_some() => {
+                    Operand::Range(register(x).unwrap_or(0), register(y).unwrap_or(0))
+                }
+                _ => Operand::Value(evaluate(token, symbols, line)?, token.column),
+            },
+        };
+        Ok(operand)
+    }
+
+    // Checks that a value fits in `bits` bits, bytes may also be given as negative numbers
+    fn fit(value: i64, bits: u32, line: usize, column: usize) -> Result<u16, AsmError> {
+        let max = (1 << bits) - 1;
+        let min = if bits == 8 { -128 } else { 0 };
+        if value < min || value > max {
+            return Err(error(
+                line,
+                column,
+                &format!("Value {:#X} does not fit in {} bits", value, bits),
+            ));
+        }
+        Ok((value & max) as u16)
+    }
+
+    fn encode(statement: &Statement, symbols: &HashMap<String, i64>) -> Result<Vec<u8>, AsmError> {
+        let line = statement.line;
+        let head = &statement.tokens[0];
+        let args = &statement.tokens[1..];
+        let mnemonic = head.text.to_ascii_uppercase();
+
+        match mnemonic.as_str() {
+            ":BYTE" | "DB" => {
+                return args
+                    .iter()
+                    .map(|t| Ok(fit(evaluate(t, symbols, line)?, 8, line, t.column)? as u8))
+                    .collect();
+            }
+            ":WORD" | "DW" => {
+                let mut bytes = Vec::new();
+                for t in args {
+                    let word = fit(evaluate(t, symbols, line)?, 16, line, t.column)?;
+                    bytes.extend_from_slice(&word.to_be_bytes());
+                }
+                return Ok(bytes);
+            }
+            ":SPRITE" => {
+                let mut bytes = Vec::new();
+                for row in args {
+                    bytes.extend(sprite_row(row, line)?);
+                }
+                return Ok(bytes);
+            }
+            _ => {}
+        }
+
+        let operands = args
+            .iter()
+            .map(|t| operand(t, symbols, line))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let n = |value: i64, column| fit(value, 4, line, column);
+        let nn = |value: i64, column| fit(value, 8, line, column);
+        let nnn = |value: i64, column| fit(value, 12, line, column);
+
+        use Operand::*;
+        let opcode = match (mnemonic.as_str(), operands.as_slice()) {
+            ("CLS", []) => 0x00E0,
+            ("RET", []) => 0x00EE,
+            ("SCD", [Value(v, c)]) => 0x00C0 | n(*v, *c)?,
+            ("SCU", [Value(v, c)]) => 0x00D0 | n(*v, *c)?,
+            ("SCR", []) => 0x00FB,
+            ("SCL", []) => 0x00FC,
+            ("EXIT", []) => 0x00FD,
+            ("LOW", []) => 0x00FE,
+            ("HIGH", []) => 0x00FF,
+            ("BGC", []) => 0x02A0,
+            ("SYS", [Value(v, c)]) => nnn(*v, *c)?,
+            ("JP", [Value(v, c)]) => 0x1000 | nnn(*v, *c)?,
+            ("JP", [V(0), Value(v, c)]) => 0xB000 | nnn(*v, *c)?,
+            ("CALL", [Value(v, c)]) => 0x2000 | nnn(*v, *c)?,
+            ("SE", [V(x), Value(v, c)]) => 0x3000 | x << 8 | nn(*v, *c)?,
+            ("SNE", [V(x), Value(v, c)]) => 0x4000 | x << 8 | nn(*v, *c)?,
+            ("SE", [V(x), V(y)]) => 0x5000 | x << 8 | y << 4,
+            ("LD", [Memory, Range(x, y)]) => 0x5002 | x << 8 | y << 4,
+            ("LD", [Range(x, y), Memory]) => 0x5003 | x << 8 | y << 4,
+            ("LD", [V(x), Value(v, c)]) => 0x6000 | x << 8 | nn(*v, *c)?,
+            ("ADD", [V(x), Value(v, c)]) => 0x7000 | x << 8 | nn(*v, *c)?,
+            ("LD", [V(x), V(y)]) => 0x8000 | x << 8 | y << 4,
+            ("OR", [V(x), V(y)]) => 0x8001 | x << 8 | y << 4,
+            ("AND", [V(x), V(y)]) => 0x8002 | x << 8 | y << 4,
+            ("XOR", [V(x), V(y)]) => 0x8003 | x << 8 | y << 4,
+            ("ADD", [V(x), V(y)]) => 0x8004 | x << 8 | y << 4,
+            ("SUB", [V(x), V(y)]) => 0x8005 | x << 8 | y << 4,
+            ("SHR", [V(x), V(y)]) => 0x8006 | x << 8 | y << 4,
+            ("SHR", [V(x)]) => 0x8006 | x << 8 | x << 4,
+            ("SUBN", [V(x), V(y)]) => 0x8007 | x << 8 | y << 4,
+            ("SHL", [V(x), V(y)]) => 0x800E | x << 8 | y << 4,
+            ("SHL", [V(x)]) => 0x800E | x << 8 | x << 4,
+            ("SNE", [V(x), V(y)]) => 0x9000 | x << 8 | y << 4,
+            ("LD", [I, Long, Value(v, c)]) => {
+                let address = fit(*v, 16, line, *c)?;
+                return Ok(vec![0xF0, 0x00, (address >> 8) as u8, address as u8]);
+            }
+            ("LD", [I, Value(v, c)]) => 0xA000 | nnn(*v, *c)?,
+            ("COL", [V(x), V(y), Value(v, c)]) => 0xB000 | x << 8 | y << 4 | n(*v, *c)?,
+            ("RND", [V(x), Value(v, c)]) => 0xC000 | x << 8 | nn(*v, *c)?,
+            ("DRW", [V(x), V(y), Value(v, c)]) => 0xD000 | x << 8 | y << 4 | n(*v, *c)?,
+            ("SKP", [V(x)]) => 0xE09E | x << 8,
+            ("SKNP", [V(x)]) => 0xE0A1 | x << 8,
+            ("PLANE", [Value(v, c)]) => 0xF001 | fit(*v, 2, line, *c)? << 8,
+            ("AUDIO", []) => 0xF002,
+            ("LD", [V(x), Dt]) => 0xF007 | x << 8,
+            ("LD", [V(x), K]) => 0xF00A | x << 8,
+            ("LD", [Dt, V(x)]) => 0xF015 | x << 8,
+            ("LD", [St, V(x)]) => 0xF018 | x << 8,
+            ("ADD", [I, V(x)]) => 0xF01E | x << 8,
+            ("LD", [F, V(x)]) => 0xF029 | x << 8,
+            ("LD", [Hf, V(x)]) => 0xF030 | x << 8,
+            ("LD", [B, V(x)]) => 0xF033 | x << 8,
+            ("PITCH", [V(x)]) => 0xF03A | x << 8,
+            ("LD", [Memory, V(x)]) => 0xF055 | x << 8,
+            ("LD", [V(x), Memory]) => 0xF065 | x << 8,
+            ("LD", [R, V(x)]) => 0xF075 | x << 8,
+            ("LD", [V(x), R]) => 0xF085 | x << 8,
+            ("OUT", [V(x)]) => 0xF0F8 | x << 8,
+            ("IN", [V(x)]) => 0xF0FB | x << 8,
+            (
+                "CLS" | "RET" | "SCD" | "SCU" | "SCR" | "SCL" | "EXIT" | "LOW" | "HIGH" | "BGC"
+                | "SYS" | "JP" | "CALL" | "SE" | "SNE" | "LD" | "ADD" | "OR" | "AND" | "XOR"
+                | "SUB" | "SHR" | "SUBN" | "SHL" | "COL" | "RND" | "DRW" | "SKP" | "SKNP" | "PLANE"
+                | "AUDIO" | "PITCH" | "OUT" | "IN",
+                _,
+            ) => {
+                return Err(error(
+                    line,
+                    head.column,
+                    &format!("Invalid operands for {}", mnemonic),
+                ))
+            }
+            _ => {
+                return Err(error(
+                    line,
+                    head.column,
+                    &format!("Unknown instruction \"{}\"", head.text),
+                ))
+            }
+        };
+
+        Ok(opcode.to_be_bytes().to_vec())
+    }
+
+    fn sprite_row(row: &Token, line: usize) -> Result<Vec<u8>, AsmError> {
+        if row.text.len() > 16 {
+            return Err(error(
+                line,
+                row.column,
+                "Sprite rows are at most 16 pixels wide",
+            ));
+        }
+
+        let mut bits: u16 = 0;
+        for (i, c) in row.text.chars().enumerate() {
+            let set = match c {
+                'X' | 'x' | '#' | '1' => true,
+                '.' | '0' => false,
+                _ => {
+                    return Err(error(
+                        line,
+                        row.column + i,
+                        &format!("Invalid sprite pixel '{}'", c),
+                    ))
+                }
+            };
+            bits = bits << 1 | set as u16;
+        }
+
+        if row.text.len() > 8 {
+            Ok((bits << (16 - row.text.len())).to_be_bytes().to_vec())
+        } else {
+            Ok(vec![(bits << (8 - row.text.len())) as u8])
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::chip8::{
+            disassembler::disassembly::{disassemble, Syntax},
+            platform::variant::Platform,
+        };
+        use std::collections::BTreeSet;
+
+        fn assert_error(source: &str, line: usize, column: usize, message: &str) {
+            let error = assemble(source).unwrap_err();
+            assert_eq!((error.line, error.column), (line, column), "{}", error);
+            assert_eq!(error.message, message);
+        }
+
+        #[test]
+        fn every_disassembled_instruction_assembles_back() {
+            let mut mnemonics = BTreeSet::new();
+            for platform in [Platform::Chip8, Platform::Chip8X] {
+                for opcode in 0..=u16::MAX {
+                    let mut memory = opcode.to_be_bytes().to_vec();
+                    memory.extend([0xAB, 0xCD]);
+                    let (text, size) = disassemble(&memory, 0, platform, Syntax::Classic);
+                    if text.starts_with("DW ") {
+                        continue;
+                    }
+
+                    let rom = assemble(&text).unwrap_or_else(|e| panic!("{}: {}", text, e));
+                    assert_eq!(rom, memory[..size], "{}", text);
+                    mnemonics.insert(text.split(' ').next().unwrap_or_default().to_string());
+                }
+            }
+
+            let expected = [
+                "ADD", "AND", "AUDIO", "BGC", "CALL", "CLS", "COL", "DRW", "EXIT", "HIGH", "IN",
+                "JP", "LD", "LOW", "OR", "OUT", "PITCH", "PLANE", "RET", "RND", "SCD", "SCL",
+                "SCR", "SCU", "SE", "SHL", "SHR", "SKNP", "SKP", "SNE", "SUB", "SUBN", "XOR",
+            ];
+            assert_eq!(mnemonics, expected.iter().map(|m| m.to_string()).collect());
+        }
+
+        #[test]
+        fn assembles_the_forms_the_disassembler_does_not_print() {
+            let source = "SYS 0x123\nSHR V3\nSHL V4\nld v1, -1\nLD I, LONG label\nlabel:";
+            assert_eq!(
+                assemble(source).unwrap(),
+                [0x01, 0x23, 0x83, 0x36, 0x84, 0x4E, 0x61, 0xFF, 0xF0, 0x00, 0x02, 0x0C]
+            );
+        }
+
+        #[test]
+        fn labels_resolve_forward_and_backward() {
+            let source = "\
+start:  JP end      ; forward reference
+loop:   CALL loop
+        JP start+2
+end:    LD I, data-1
+data:   DB 1";
+            assert_eq!(
+                assemble(source).unwrap(),
+                [0x12, 0x06, 0x22, 0x02, 0x12, 0x02, 0xA2, 0x07, 0x01]
+            );
+            assert_eq!(assemble_at("here: JP here", 0x600).unwrap(), [0x16, 0x00]);
+        }
+
+        #[test]
+        fn directives_emit_data() {
+            let source = "\
+:const SPEED 3
+:const DOUBLE SPEED+SPEED
+        LD V0, DOUBLE
+:org 0x208
+        :byte 1 0xFF -1, 0b101
+        :word 0x1234 end
+        :sprite X.X.X.X. ####....####.... 1.
+end:";
+            assert_eq!(
+                assemble(source).unwrap(),
+                [
+                    0x60, 0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // LD, gap up to :org
+                    0x01, 0xFF, 0xFF, 0x05, // :byte
+                    0x12, 0x34, 0x02, 0x14, // :word
+                    0xAA, 0xF0, 0xF0, 0x80, // :sprite
+                ]
+            );
+        }
+
+        #[test]
+        fn errors_point_at_the_line_and_column() {
+            assert_error(
+                "CLS\n  LD V1, nope",
+                2,
+                10,
+                "Unknown label or constant \"nope\"",
+            );
+            assert_error("LD V1, 2+nope", 1, 10, "Unknown label or constant \"nope\"");
+            assert_error("LD V1, 0x1G", 1, 8, "Invalid value \"0x1G\"");
+            assert_error("\n\n   FOO V1", 3, 4, "Unknown instruction \"FOO\"");
+            assert_error("DRW V0, V1", 1, 1, "Invalid operands for DRW");
+            assert_error("ADD V2, 256", 1, 9, "Value 0x100 does not fit in 8 bits");
+            assert_error("a:\na:", 2, 1, "\"a\" is already defined");
+            assert_error(
+                ":org 0x100",
+                1,
+                6,
+                "Address 0x100 is outside of the program",
+            );
+            assert_error(":sprite X.?", 1, 11, "Invalid sprite pixel '?'");
+            assert_error(":const X", 1, 1, ":const takes a name and a value");
+        }
+
+        #[test]
+        fn values_out_of_range_are_errors() {
+            let message = "Value \"0x7FFFFFFFFFFFFFFF+1\" is out of range";
+            assert_error("LD V0, 0x7FFFFFFFFFFFFFFF+1", 1, 8, message);
+            let source = ":const MIN -0x7FFFFFFFFFFFFFFF-1\nLD V0, -MIN";
+            assert_error(source, 2, 8, "Value \"-MIN\" is out of range");
+        }
+    }
+}
@@ -5,8 +5,11 @@ pub mod audio_driver {
         AudioSubsystem,
     };
 
+    // Bits in an XO-CHIP audio pattern
+    const PATTERN_BITS: f32 = 128.;
+
     pub struct Audio {
-        audio_device: AudioDevice<SquareWave>,
+        audio_device: AudioDevice<Tone>,
     }
 
     impl Audio {
@@ -20,10 +23,13 @@ pub mod audio_driver {
             let device = audio_subsystem
                 .open_playback(None, &desired_spec, |spec| {
                     // newialize the audio callback
-                    SquareWave {
+                    Tone {
                         phase_inc: 240. / spec.freq as f32,
                         phase: 0.,
                         volume: 0.25,
+                        pattern: None,
+                        pattern_phase: 0.,
+                        pattern_phase_inc: 0.,
                     }
                 })
                 .unwrap();
@@ -33,8 +39,15 @@ pub mod audio_driver {
             }
         }
 
-        pub fn play(&self, chip8: &mut Chip8) {
+        pub fn play(&mut self, chip8: &mut Chip8) {
             if chip8.should_play_sound() {
+                // Follow any XO-CHIP pattern or pitch changes before playing
+                let freq = self.audio_device.spec().freq as f32;
+                let mut tone = self.audio_device.lock();
+                tone.pattern = chip8.audio_pattern().copied();
+                tone.pattern_phase_inc = chip8.audio_pattern_rate() / freq;
+                drop(tone);
+
                 // play
                 self.audio_device.resume();
             } else {
@@ -44,20 +57,38 @@ pub mod audio_driver {
         }
     }
 
-    struct SquareWave {
+    // A square wave, or the XO-CHIP audio pattern once a program has loaded one
+    struct Tone {
         phase_inc: f32,
         phase: f32,
         volume: f32,
+        pattern: Option<[u8; 16]>,
+        pattern_phase: f32,
+        pattern_phase_inc: f32,
     }
 
-    impl AudioCallback for SquareWave {
+    impl AudioCallback for Tone {
         type Channel = f32;
 
         fn callback(&mut self, out: &mut [f32]) {
-            // Generate a square wave
-            for x in out.iter_mut() {
-                *x = self.volume * if self.phase < 0.5 { 1.0 } else { -1.0 };
-                self.phase = (self.phase + self.phase_inc) % 1.0;
+            match self.pattern {
+                Some(pattern) => {
+                    // Play the pattern one bit at a time, most significant bit first
+                    for x in out.iter_mut() {
+                        let bit = self.pattern_phase as usize;
+                        let high = pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+                        *x = self.volume * if high { 1.0 } else { -1.0 };
+                        self.pattern_phase =
+                            (self.pattern_phase + self.pattern_phase_inc) % PATTERN_BITS;
+                    }
+                }
+                None => {
+                    // Generate a square wave
+                    for x in out.iter_mut() {
+                        *x = self.volume * if self.phase < 0.5 { 1.0 } else { -1.0 };
+                        self.phase = (self.phase + self.phase_inc) % 1.0;
+                    }
+                }
             }
         }
     }
@@ -0,0 +1,270 @@
+pub mod rom_database {
+    use crate::chip8::{platform::variant::Platform, quirks::quirk_profile::Quirks};
+    use serde_json::Value;
+    use sha1::{Digest, Sha1};
+    use std::collections::HashMap;
+
+    // Database shipped with the emulator, in the chip-8-database programs.json format
+    const BUNDLED: &str = include_str!("../../assets/programs.json");
+
+    /// Lowercase hex SHA-1 of a ROM, the key it is looked up by
+    pub fn rom_hash(rom: &[u8]) -> String {
+        Sha1::digest(rom)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Settings the database holds for a ROM, `None` where it leaves the choice to the user
+    #[derive(Clone, Debug, Default, PartialEq, Eq)]
+    pub struct RomSettings {
+        pub title: String,
+        pub platform: Option<Platform>,
+        pub quirks: Option<Quirks>,
+        /// Instructions per frame, the database's tickrate
+        pub cycles_per_frame: Option<usize>,
+        /// Background and plane colours as `0xRRGGBB`, as many as the database lists
+        pub colours: Vec<u32>,
+        /// Hex keys the ROM uses for `up`, `down`, `left`, `right`, `a` and `b`
+        pub keys: Vec<(String, u8)>,
+    }
+
+    /// ROM settings keyed by SHA-1, read from the JSON of the community
+    /// [chip-8-database](https://github.com/chip-8/chip-8-database)
+    ///
+    /// Only `programs.json` is needed. Each program lists its ROMs by hash with the platforms
+    /// they run on, best first, and optionally a tickrate, colours, key hints and quirk
+    /// overrides per platform
+    #[derive(Clone, Debug, Default)]
+    pub struct Database {
+        roms: HashMap<String, RomSettings>,
+    }
+
+    impl RomSettings {
+        /// `palette` with its first colours replaced by the ones the database lists
+        pub fn palette(&self, mut palette: [u32; 4]) -> [u32; 4] {
+            for (slot, &colour) in palette.iter_mut().zip(&self.colours) {
+                *slot = colour;
+            }
+            palette
+        }
+    }
+
+    impl Database {
+        /// The database shipped with the emulator
+        pub fn bundled() -> Database {
+            let mut database = Database::default();
+            database
+                .extend_from_json(BUNDLED)
+                .expect("bundled ROM database is valid");
+            database
+        }
+
+        /// Adds the ROMs of a programs.json, replacing settings already held for the same hash
+        pub fn extend_from_json(&mut self, json: &str) -> Result<(), String> {
+            let programs: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+            let programs = programs
+                .as_array()
+                .ok_or_else(|| "Expected a list of programs".to_string())?;
+
+            for program in programs {
+                let title = program["title"].as_str().unwrap_or("Untitled");
+                let roms = match program["roms"].as_object() {
+                    Some(roms) => roms,
+                    None => continue,
+                };
+                for (hash, rom) in roms {
+                    self.roms
+                        .insert(hash.to_ascii_lowercase(), parse_rom(title, rom));
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Number of ROMs known
+        pub fn len(&self) -> usize {
+            self.roms.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.roms.is_empty()
+        }
+
+        pub fn lookup(&self, rom: &[u8]) -> Option<&RomSettings> {
+            self.roms.get(&rom_hash(rom))
+        }
+    }
+
+    fn parse_rom(title: &str, rom: &Value) -> RomSettings {
+        let mut settings = RomSettings {
+            title: title.to_string(),
+            cycles_per_frame: rom["tickrate"]
+                .as_u64()
+                .filter(|&tickrate| tickrate > 0)
+                .map(|tickrate| tickrate as usize),
+            ..RomSettings::default()
+        };
+
+        // The first platform this interpreter supports decides the platform and quirks
+        let platforms = rom["platforms"].as_array().into_iter().flatten();
+        if let Some((id, platform, quirks)) = platforms
+            .filter_map(Value::as_str)
+            .find_map(|id| platform_settings(id).map(|(p, q)| (id, p, q)))
+        {
+            settings.platform = Some(platform);
+            settings.quirks = Some(override_quirks(quirks, &rom["quirkyPlatforms"][id]));
+        }
+
+        settings.colours = rom["colors"]["pixels"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .filter_map(|colour| u32::from_str_radix(colour.trim_start_matches('#'), 16).ok())
+            .collect();
+
+        if let Some(keys) = rom["keys"].as_object() {
+            settings.keys = keys
+                .iter()
+                .filter_map(|(name, key)| Some((name.clone(), key.as_u64().filter(|&k| k < 16)?)))
+                .map(|(name, key)| (name, key as u8))
+                .collect();
+        }
+
+        settings
+    }
+
+    // Platform and quirks of a chip-8-database platform id
+    fn platform_settings(id: &str) -> Option<(Platform, Quirks)> {
+        match id {
+            "originalChip8" | "hybridVIP" => Some((Platform::Chip8, Quirks::COSMAC_VIP)),
+            "modernChip8" => Some((
+                Platform::Chip8,
+                Quirks {
+                    vf_reset: false,
+                    display_wait: false,
+                    ..Quirks::COSMAC_VIP
+                },
+            )),
+            "chip8x" => Some((Platform::Chip8X, Quirks::COSMAC_VIP)),
+            "chip48" | "superchip1" | "superchip" => Some((Platform::Chip8, Quirks::SUPER_CHIP)),
+            "xochip" => Some((Platform::Chip8, Quirks::XO_CHIP)),
+            _ => None,
+        }
+    }
+
+    // Applies a quirkyPlatforms entry, quirks it does not mention keep the platform's value
+    fn override_quirks(mut quirks: Quirks, overrides: &Value) -> Quirks {
+        let quirk = |name: &str| overrides[name].as_bool();
+        if let Some(shift) = quirk("shift") {
+            quirks.shift = shift;
+        }
+        if let Some(unchanged) = quirk("memoryLeaveIUnchanged") {
+            quirks.load_store = !unchanged;
+        }
+        if let Some(wrap) = quirk("wrap") {
+            quirks.clipping = !wrap;
+        }
+        if let Some(jump) = quirk("jump") {
+            quirks.jump = jump;
+        }
+        if let Some(vblank) = quirk("vblank") {
+            quirks.display_wait = vblank;
+        }
+        if let Some(logic) = quirk("logic") {
+            quirks.vf_reset = logic;
+        }
+        quirks
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::chip8::assembler::assembly::assemble;
+
+        const ROM: [u8; 4] = [0x00, 0xE0, 0x12, 0x00];
+
+        fn database() -> Database {
+            let json = format!(
+                r##"[
+                    {{
+                        "title": "Test Program",
+                        "roms": {{
+                            "{}": {{
+                                "platforms": ["megachip8", "superchip", "xochip"],
+                                "tickrate": 30,
+                                "colors": {{ "pixels": ["#101010", "#ff8000"] }},
+                                "keys": {{ "up": 5, "a": 6, "start": 16 }},
+                                "quirkyPlatforms": {{
+                                    "superchip": {{ "shift": false, "wrap": true }}
+                                }}
+                            }}
+                        }}
+                    }},
+                    {{ "title": "No ROMs" }}
+                ]"##,
+                rom_hash(&ROM).to_ascii_uppercase()
+            );
+            let mut database = Database::default();
+            database.extend_from_json(&json).unwrap();
+            database
+        }
+
+        #[test]
+        fn hashes_are_lowercase_sha1() {
+            assert_eq!(rom_hash(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        }
+
+        #[test]
+        fn known_rom_resolves_to_its_settings() {
+            let database = database();
+            assert_eq!(database.len(), 1);
+
+            let settings = database.lookup(&ROM).unwrap();
+            assert_eq!(settings.title, "Test Program");
+            // megachip8 is not supported, so the next platform decides
+            assert_eq!(settings.platform, Some(Platform::Chip8));
+            assert_eq!(
+                settings.quirks,
+                Some(Quirks {
+                    shift: false,
+                    clipping: false,
+                    ..Quirks::SUPER_CHIP
+                })
+            );
+            assert_eq!(settings.cycles_per_frame, Some(30));
+            assert_eq!(settings.colours, [0x101010, 0xFF8000]);
+            assert_eq!(settings.palette([0, 1, 2, 3]), [0x101010, 0xFF8000, 2, 3]);
+            let mut keys = settings.keys.clone();
+            keys.sort();
+            assert_eq!(keys, [("a".to_string(), 6), ("up".to_string(), 5)]);
+        }
+
+        #[test]
+        fn unknown_rom_is_not_found() {
+            assert!(database().lookup(&ROM[..2]).is_none());
+        }
+
+        #[test]
+        fn invalid_json_is_an_error() {
+            let mut database = Database::default();
+            assert!(database.extend_from_json("{").is_err());
+            assert!(database.extend_from_json("{}").is_err());
+            assert!(database.is_empty());
+        }
+
+        #[test]
+        fn bundled_database_recognises_the_demo() {
+            let rom = assemble(include_str!("../../assets/bounce.asm")).unwrap();
+            assert_eq!(rom_hash(&rom), "8a8b144ca4e0959a05ed26aa67dc5381d305b87e");
+
+            let database = Database::bundled();
+            let settings = database.lookup(&rom).unwrap();
+            assert_eq!(settings.title, "Bounce");
+            assert_eq!(settings.platform, Some(Platform::Chip8));
+            assert_eq!(settings.cycles_per_frame, Some(15));
+            assert_eq!(settings.colours, [0x000000, 0xFFAA00]);
+        }
+    }
+}
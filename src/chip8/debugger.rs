@@ -0,0 +1,508 @@
+pub mod debug_console {
+    use crate::chip8::{
+        disassembler::disassembly::{disassemble, Syntax},
+        emulator::core::Emulator,
+        processor::chip::{Writes, MEMORY_SIZE},
+    };
+    use std::{collections::BTreeSet, fmt::Write};
+
+    const HELP: &str = "\
+step [N]          execute N instructions, 1 by default
+continue          run until a breakpoint or watchpoint is hit
+pause             stop running
+break ADDR        break before the instruction at ADDR executes
+break op PATTERN  break before an opcode matching PATTERN, x matches any digit (Dxy0)
+delete ADDR       remove a breakpoint, delete op PATTERN removes an opcode breakpoint
+watch ADDR|VX|I   stop after an instruction writes the memory address or register
+unwatch ADDR|VX|I remove a watchpoint
+info              list breakpoints and watchpoints
+regs              print the registers, I, PC, the stack and the timers
+mem ADDR [LEN]    dump LEN bytes of memory from ADDR, 64 by default
+list [ADDR]       disassemble around ADDR, the program counter by default
+quit              exit the emulator
+An empty line repeats the last command, addresses and patterns are hex";
+
+    // Instructions listed before and after the address by `list`
+    const LIST_CONTEXT: usize = 5;
+    const DEFAULT_DUMP_LENGTH: usize = 64;
+
+    // An opcode breakpoint, matching the opcodes where the bits in `mask` equal `value`
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    struct OpcodePattern {
+        value: u16,
+        mask: u16,
+    }
+
+    impl OpcodePattern {
+        fn parse(pattern: &str) -> Option<OpcodePattern> {
+            if pattern.len() != 4 {
+                return None;
+            }
+
+            let mut value = 0;
+            let mut mask = 0;
+            for c in pattern.chars() {
+                value <<= 4;
+                mask <<= 4;
+                if !c.eq_ignore_ascii_case(&'x') {
+                    value |= c.to_digit(16)? as u16;
+                    mask |= 0xF;
+                }
+            }
+            Some(OpcodePattern { value, mask })
+        }
+
+        fn matches(&self, opcode: u16) -> bool {
+            opcode & self.mask == self.value
+        }
+    }
+
+    impl std::fmt::Display for OpcodePattern {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            for digit in (0..4).rev() {
+                let shift = digit * 4;
+                if self.mask >> shift & 0xF == 0 {
+                    write!(f, "x")?;
+                } else {
+                    write!(f, "{:X}", self.value >> shift & 0xF)?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    enum Watch {
+        Memory(u16),
+        Register(u8),
+        Index,
+    }
+
+    impl Watch {
+        fn parse(target: &str) -> Option<Watch> {
+            if target.eq_ignore_ascii_case("i") {
+                Some(Watch::Index)
+            } else if target.len() == 2 && target.starts_with(['v', 'V']) {
+                u8::from_str_radix(&target[1..], 16)
+                    .ok()
+                    .map(Watch::Register)
+            } else {
+                parse_address(target).map(Watch::Memory)
+            }
+        }
+
+        fn written(&self, writes: &Writes) -> bool {
+            match *self {
+                Watch::Memory(address) => writes.memory(address),
+                Watch::Register(register) => writes.register(register),
+                Watch::Index => writes.index(),
+            }
+        }
+
+        fn value(&self, emulator: &Emulator) -> u16 {
+            let chip8 = emulator.chip8();
+            match *self {
+                Watch::Memory(address) => chip8.memory()[address as usize] as u16,
+                Watch::Register(register) => chip8.registers()[register as usize] as u16,
+                Watch::Index => chip8.ir(),
+            }
+        }
+    }
+
+    impl std::fmt::Display for Watch {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            match self {
+                Watch::Memory(address) => write!(f, "[{:#06X}]", address),
+                Watch::Register(register) => write!(f, "V{:X}", register),
+                Watch::Index => write!(f, "I"),
+            }
+        }
+    }
+
+    /// Pauses an emulator and drives it from text commands, stopping at breakpoints on
+    /// addresses or opcodes and after instructions write watched memory and registers
+    pub struct Debugger {
+        breakpoints: BTreeSet<u16>,
+        opcode_breakpoints: Vec<OpcodePattern>,
+        watches: BTreeSet<Watch>,
+        paused: bool,
+        quit: bool,
+        // Address of the breakpoint execution stopped at, stepped over when continuing
+        stopped_at: Option<u16>,
+        last_command: String,
+    }
+
+    impl Default for Debugger {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl Debugger {
+        /// A debugger with no breakpoints, starting paused
+        pub fn new() -> Debugger {
+            Debugger {
+                breakpoints: BTreeSet::new(),
+                opcode_breakpoints: Vec::new(),
+                watches: BTreeSet::new(),
+                paused: true,
+                quit: false,
+                stopped_at: None,
+                last_command: String::new(),
+            }
+        }
+
+        pub const fn is_paused(&self) -> bool {
+            self.paused
+        }
+
+        /// Whether the quit command was given
+        pub const fn has_quit(&self) -> bool {
+            self.quit
+        }
+
+        /// Stops execution, such as when the emulator faulted outside of the debugger
+        pub fn pause(&mut self) {
+            self.paused = true;
+        }
+
+        /// Runs the rest of the current frame unless paused, returning why execution stopped
+        /// when it hits a breakpoint, a watchpoint or a fault
+        pub fn run_frame(&mut self, emulator: &mut Emulator) -> Option<String> {
+            if self.paused {
+                return None;
+            }
+
+            loop {
+                let pc = emulator.chip8().pc();
+                if self.stopped_at != Some(pc) {
+                    if let Some(hit) = self.breakpoint_hit(emulator) {
+                        self.paused = true;
+                        self.stopped_at = Some(pc);
+                        return Some(format!("{}\n{}", hit, current(emulator)));
+                    }
+                }
+                self.stopped_at = None;
+
+                match self.step(emulator) {
+                    Ok(true) => return None,
+                    Ok(false) => {}
+                    Err(stop) => {
+                        self.paused = true;
+                        return Some(format!("{}\n{}", stop, current(emulator)));
+                    }
+                }
+            }
+        }
+
+        /// Executes a command line, returning its output
+        pub fn execute(&mut self, line: &str, emulator: &mut Emulator) -> String {
+            let line = line.trim();
+            let line = if line.is_empty() {
+                self.last_command.clone()
+            } else {
+                self.last_command = line.to_string();
+                line.to_string()
+            };
+
+            let args: Vec<&str> = line.split_whitespace().collect();
+            let result = match args.as_slice() {
+                [] => Ok(String::new()),
+                ["s" | "step"] => Ok(self.step_instructions(emulator, 1)),
+                ["s" | "step", count] => count
+                    .parse()
+                    .map(|count| self.step_instructions(emulator, count))
+                    .map_err(|_| format!("Invalid count \"{}\"", count)),
+                ["c" | "continue"] => {
+                    self.paused = false;
+                    Ok("Continuing".to_string())
+                }
+                ["p" | "pause"] => {
+                    self.paused = true;
+                    Ok(current(emulator))
+                }
+                ["b" | "break", "op", pattern] => self.add_opcode_breakpoint(pattern),
+                ["b" | "break", address] => parse_address(address)
+                    .map(|address| {
+                        self.breakpoints.insert(address);
+                        format!("Breakpoint at {:#06X}", address)
+                    })
+                    .ok_or_else(|| format!("Invalid address \"{}\"", address)),
+                ["d" | "delete", "op", pattern] => OpcodePattern::parse(pattern)
+                    .filter(|pattern| self.opcode_breakpoints.contains(pattern))
+                    .map(|pattern| {
+                        self.opcode_breakpoints.retain(|&p| p != pattern);
+                        format!("Deleted breakpoint on opcode {}", pattern)
+                    })
+                    .ok_or_else(|| format!("No breakpoint on opcode \"{}\"", pattern)),
+                ["d" | "delete", address] => parse_address(address)
+                    .filter(|address| self.breakpoints.remove(address))
+                    .map(|address| format!("Deleted breakpoint at {:#06X}", address))
+                    .ok_or_else(|| format!("No breakpoint at \"{}\"", address)),
+                ["w" | "watch", target] => Watch::parse(target)
+                    .map(|watch| {
+                        self.watches.insert(watch);
+                        format!("Watching {}", watch)
+                    })
+                    .ok_or_else(|| format!("Invalid watch target \"{}\"", target)),
+                ["unwatch", target] => Watch::parse(target)
+                    .filter(|watch| self.watches.remove(watch))
+                    .map(|watch| format!("Stopped watching {}", watch))
+                    .ok_or_else(|| format!("Not watching \"{}\"", target)),
+                ["info"] => Ok(self.info()),
+                ["r" | "regs"] => Ok(registers(emulator)),
+                ["m" | "mem", address] => dump(emulator, address, None),
+                ["m" | "mem", address, length] => dump(emulator, address, Some(length)),
+                ["l" | "list"] => Ok(list(emulator, emulator.chip8().pc())),
+                ["l" | "list", address] => parse_address(address)
+                    .map(|address| list(emulator, address))
+                    .ok_or_else(|| format!("Invalid address \"{}\"", address)),
+                ["q" | "quit"] => {
+                    self.quit = true;
+                    Ok(String::new())
+                }
+                ["h" | "help"] => Ok(HELP.to_string()),
+                _ => Err(format!("Unknown command \"{}\", try help", line)),
+            };
+
+            result.unwrap_or_else(|e| e)
+        }
+
+        fn add_opcode_breakpoint(&mut self, pattern: &str) -> Result<String, String> {
+            let pattern = OpcodePattern::parse(pattern)
+                .ok_or_else(|| format!("Invalid opcode pattern \"{}\"", pattern))?;
+            if !self.opcode_breakpoints.contains(&pattern) {
+                self.opcode_breakpoints.push(pattern);
+            }
+            Ok(format!("Breakpoint on opcode {}", pattern))
+        }
+
+        fn info(&self) -> String {
+            let mut text = String::new();
+            for address in &self.breakpoints {
+                let _ = writeln!(text, "Breakpoint at {:#06X}", address);
+            }
+            for pattern in &self.opcode_breakpoints {
+                let _ = writeln!(text, "Breakpoint on opcode {}", pattern);
+            }
+            for watch in &self.watches {
+                let _ = writeln!(text, "Watching {}", watch);
+            }
+            if text.is_empty() {
+                text.push_str("No breakpoints or watchpoints");
+            }
+            text.trim_end().to_string()
+        }
+
+        // Steps while paused, stopping early at watchpoints and faults but not breakpoints
+        fn step_instructions(&mut self, emulator: &mut Emulator, count: usize) -> String {
+            self.paused = true;
+            self.stopped_at = None;
+            for _ in 0..count {
+                if let Err(stop) = self.step(emulator) {
+                    return format!("{}\n{}", stop, current(emulator));
+                }
+            }
+            current(emulator)
+        }
+
+        // Executes one instruction, returning whether it completed a frame, or the watchpoints
+        // it triggered or its fault
+        fn step(&self, emulator: &mut Emulator) -> Result<bool, String> {
+            let address = emulator.chip8().pc();
+            let before: Vec<u16> = self.watches.iter().map(|w| w.value(emulator)).collect();
+
+            let frame_done = emulator
+                .step_in_frame()
+                .map_err(|e| format!("Fault: {}", e))?;
+
+            let mut triggered = String::new();
+            let writes = emulator.chip8().writes();
+            for (watch, before) in self.watches.iter().zip(before) {
+                if watch.written(writes) {
+                    let _ = writeln!(
+                        triggered,
+                        "Watchpoint {} written at {:#06X}, {:#X} to {:#X}",
+                        watch,
+                        address,
+                        before,
+                        watch.value(emulator)
+                    );
+                }
+            }
+            if triggered.is_empty() {
+                Ok(frame_done)
+            } else {
+                Err(triggered.trim_end().to_string())
+            }
+        }
+
+        fn breakpoint_hit(&self, emulator: &Emulator) -> Option<String> {
+            let pc = emulator.chip8().pc();
+            let opcode = opcode_at(emulator, pc);
+
+            if self.breakpoints.contains(&pc) {
+                Some(format!("Breakpoint at {:#06X}", pc))
+            } else {
+                self.opcode_breakpoints
+                    .iter()
+                    .find(|pattern| pattern.matches(opcode))
+                    .map(|pattern| format!("Breakpoint on opcode {} at {:#06X}", pattern, pc))
+            }
+        }
+    }
+
+    // Accepts hex addresses with or without a 0x prefix
+    fn parse_address(text: &str) -> Option<u16> {
+        let hex = text.trim_start_matches("0x").trim_start_matches("0X");
+        u16::from_str_radix(hex, 16).ok()
+    }
+
+    fn opcode_at(emulator: &Emulator, address: u16) -> u16 {
+        let memory = emulator.chip8().memory();
+        (memory[address as usize] as u16) << 8
+            | *memory.get(address as usize + 1).unwrap_or(&0) as u16
+    }
+
+    // The instruction about to execute
+    fn current(emulator: &Emulator) -> String {
+        let chip8 = emulator.chip8();
+        let (text, _) = disassemble(
+            chip8.memory(),
+            chip8.pc() as usize,
+            chip8.platform(),
+            Syntax::Classic,
+        );
+        format!(
+            "{:#06X}  {:04X}  {}",
+            chip8.pc(),
+            opcode_at(emulator, chip8.pc()),
+            text
+        )
+    }
+
+    fn registers(emulator: &Emulator) -> String {
+        let chip8 = emulator.chip8();
+        let mut text = format!(
+            "PC {:#06X}  I {:#06X}  SP {}  DT {}  ST {}",
+            chip8.pc(),
+            chip8.ir(),
+            chip8.sp(),
+            chip8.delay_timer(),
+            chip8.sound_timer()
+        );
+        for (i, value) in chip8.registers().iter().enumerate() {
+            let separator = if i % 8 == 0 { "\n" } else { "  " };
+            let _ = write!(text, "{}V{:X} {:02X}", separator, i, value);
+        }
+        text.push_str("\nStack");
+        for address in chip8.stack() {
+            let _ = write!(text, " {:#06X}", address);
+        }
+        text
+    }
+
+    fn dump(emulator: &Emulator, address: &str, length: Option<&&str>) -> Result<String, String> {
+        let start = parse_address(address)
+            .ok_or_else(|| format!("Invalid address \"{}\"", address))?
+            as usize;
+        let length = match length {
+            Some(length) => length
+                .parse()
+                .map_err(|_| format!("Invalid length \"{}\"", length))?,
+            None => DEFAULT_DUMP_LENGTH,
+        };
+        let end = start.saturating_add(length).min(MEMORY_SIZE);
+
+        let mut text = String::new();
+        for (row, bytes) in emulator.chip8().memory()[start..end].chunks(16).enumerate() {
+            let _ = write!(text, "{:#06X}:", start + row * 16);
+            for byte in bytes {
+                let _ = write!(text, " {:02X}", byte);
+            }
+            text.push('\n');
+        }
+        Ok(text.trim_end().to_string())
+    }
+
+    // Disassembles from a few instructions before `address`, marking the program counter
+    fn list(emulator: &Emulator, address: u16) -> String {
+        let chip8 = emulator.chip8();
+        let mut text = String::new();
+        let mut address = (address as usize).saturating_sub(LIST_CONTEXT * 2);
+
+        for _ in 0..LIST_CONTEXT * 2 + 1 {
+            if address >= MEMORY_SIZE - 1 {
+                break;
+            }
+            let (instruction, size) =
+                disassemble(chip8.memory(), address, chip8.platform(), Syntax::Classic);
+            let marker = if address == chip8.pc() as usize {
+                ">"
+            } else {
+                " "
+            };
+            let _ = writeln!(
+                text,
+                "{} {:#06X}  {:04X}  {}",
+                marker,
+                address,
+                opcode_at(emulator, address as u16),
+                instruction
+            );
+            address += size;
+        }
+        text.trim_end().to_string()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn dump_stops_at_the_end_of_memory() {
+            let mut emulator = Emulator::new();
+            let mut debugger = Debugger::new();
+
+            let output = debugger.execute(&format!("mem FFF8 {}", usize::MAX), &mut emulator);
+
+            assert_eq!(output, "0xFFF8: 00 00 00 00 00 00 00 00");
+        }
+
+        #[test]
+        fn watchpoints_stop_on_writes_of_the_same_value() {
+            let mut emulator = Emulator::new();
+            // LD V2, 0; LD V1, 0; LD V1, 5
+            emulator
+                .load_rom(&[0x62, 0x00, 0x61, 0x00, 0x61, 0x05])
+                .unwrap();
+            let mut debugger = Debugger::new();
+            debugger.execute("watch V1", &mut emulator);
+
+            let output = debugger.execute("step 3", &mut emulator);
+
+            assert!(output.starts_with("Watchpoint V1 written at 0x0202, 0x0 to 0x0"));
+            assert_eq!(emulator.chip8().pc(), 0x204);
+        }
+
+        #[test]
+        fn watchpoints_stop_on_memory_and_index_writes() {
+            let mut emulator = Emulator::new();
+            // LD I, 0x300; LD V0, 7; LD [I], V0, storing to 0x300 and moving I with load_store
+            emulator
+                .load_rom(&[0xA3, 0x00, 0x60, 0x07, 0xF0, 0x55])
+                .unwrap();
+            let mut debugger = Debugger::new();
+            debugger.execute("watch 300", &mut emulator);
+
+            let output = debugger.execute("step 3", &mut emulator);
+            assert!(output.starts_with("Watchpoint [0x0300] written at 0x0204, 0x0 to 0x7"));
+
+            debugger.execute("unwatch 300", &mut emulator);
+            debugger.execute("watch I", &mut emulator);
+            emulator.chip8_mut().set_pc(0x200);
+            let output = debugger.execute("step 3", &mut emulator);
+            assert!(output.starts_with("Watchpoint I written at 0x0200, 0x301 to 0x300"));
+        }
+    }
+}
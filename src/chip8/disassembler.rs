@@ -0,0 +1,438 @@
+pub mod disassembly {
+    use crate::chip8::platform::variant::Platform;
+    use std::{fmt, str::FromStr};
+
+    // Data bytes shown on a single line of a listing
+    const DATA_PER_LINE: usize = 8;
+
+    /// Mnemonic syntax of the disassembly
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub enum Syntax {
+        /// The assembler syntax of Cowgod's technical reference, `LD V1, 0x0A`
+        #[default]
+        Classic,
+        /// The syntax of the Octo assembler, `v1 := 0x0A`
+        Octo,
+    }
+
+    impl FromStr for Syntax {
+        type Err = String;
+
+        fn from_str(name: &str) -> Result<Self, Self::Err> {
+            match name.to_ascii_lowercase().as_str() {
+                "classic" => Ok(Syntax::Classic),
+                "octo" => Ok(Syntax::Octo),
+                _ => Err(format!(
+                    "Unknown syntax \"{}\", expected classic or octo",
+                    name
+                )),
+            }
+        }
+    }
+
+    /// A line of a listing, either an instruction or data bytes that no traced path executes
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Line {
+        pub address: u16,
+        pub text: String,
+        pub is_code: bool,
+    }
+
+    impl fmt::Display for Line {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{:#05X}: {}", self.address, self.text)
+        }
+    }
+
+    // Where execution can go after an instruction
+    enum Flow {
+        Next,
+        Jump(usize),
+        Call(usize),
+        Skip,
+        Stop,
+    }
+
+    /// Decodes the instruction at `address`, returning its mnemonic and its size in bytes.
+    /// Words that are not instructions of `platform` are shown as data
+    pub fn disassemble(
+        memory: &[u8],
+        address: usize,
+        platform: Platform,
+        syntax: Syntax,
+    ) -> (String, usize) {
+        let opcode = word(memory, address);
+        let size = instruction_size(opcode);
+        let long = word(memory, address + 2);
+
+        let text = match syntax {
+            Syntax::Classic => {
+                classic(opcode, long, platform).unwrap_or_else(|| format!("DW {:#06X}", opcode))
+            }
+            Syntax::Octo => octo(opcode, long, platform).unwrap_or_else(|| {
+                let bytes = memory.get(address..address + size).unwrap_or_default();
+                octo_data(bytes)
+            }),
+        };
+
+        (text, size)
+    }
+
+    /// Disassembles a ROM loaded at the program start address of `platform`, tracing the
+    /// paths execution can take from there to tell code from data
+    pub fn disassemble_rom(rom: &[u8], platform: Platform, syntax: Syntax) -> Vec<Line> {
+        let start = platform.program_start();
+        let mut memory = vec![0; start + rom.len()];
+        memory[start..].copy_from_slice(rom);
+        disassemble_range(&memory, start, memory.len(), start, platform, syntax)
+    }
+
+    /// Disassembles `memory[start..end]`, following every jump, call and skip from `entry`
+    /// (recursive descent). Bytes no path reaches are listed as data
+    pub fn disassemble_range(
+        memory: &[u8],
+        start: usize,
+        end: usize,
+        entry: usize,
+        platform: Platform,
+        syntax: Syntax,
+    ) -> Vec<Line> {
+        let end = end.min(memory.len());
+        let mut instructions = vec![false; end.saturating_sub(start)];
+        let mut pending = vec![entry];
+
+        while let Some(address) = pending.pop() {
+            if address < start || address + 2 > end || instructions[address - start] {
+                continue;
+            }
+
+            let opcode = word(memory, address);
+            let size = instruction_size(opcode);
+            if address + size > end || classic(opcode, 0, platform).is_none() {
+                continue;
+            }
+            instructions[address - start] = true;
+
+            let next = address + size;
+            match flow(opcode, address, platform) {
+                Flow::Next => pending.push(next),
+                Flow::Jump(target) => pending.push(target),
+                Flow::Call(target) => pending.extend([next, target]),
+                Flow::Skip => pending.extend([next, next + instruction_size(word(memory, next))]),
+                Flow::Stop => {}
+            }
+        }
+
+        let mut lines = Vec::new();
+        let mut address = start;
+        while address < end {
+            if instructions[address - start] {
+                let (text, size) = disassemble(memory, address, platform, syntax);
+                lines.push(Line {
+                    address: address as u16,
+                    text,
+                    is_code: true,
+                });
+                address += size;
+                continue;
+            }
+
+            let data_end = (address..end)
+                .take(DATA_PER_LINE)
+                .find(|&a| instructions[a - start])
+                .unwrap_or_else(|| (address + DATA_PER_LINE).min(end));
+            let bytes = &memory[address..data_end];
+            lines.push(Line {
+                address: address as u16,
+                text: match syntax {
+                    Syntax::Classic => classic_data(bytes),
+                    Syntax::Octo => octo_data(bytes),
+                },
+                is_code: false,
+            });
+            address = data_end;
+        }
+
+        lines
+    }
+
+    fn word(memory: &[u8], address: usize) -> u16 {
+        (*memory.get(address).unwrap_or(&0) as u16) << 8
+            | *memory.get(address + 1).unwrap_or(&0) as u16
+    }
+
+    // F000 NNNN is the only instruction taking two words
+    fn instruction_size(opcode: u16) -> usize {
+        if opcode == 0xF000 {
+            4
+        } else {
+            2
+        }
+    }
+
+    fn flow(opcode: u16, address: usize, platform: Platform) -> Flow {
+        let nnn = (opcode & 0x0FFF) as usize;
+        match opcode & 0xF000 {
+            // HIRES CHIP-8 programs start with a jump into the interpreter at 0x2C0
+            0x1000
+                if platform == Platform::HiresChip8
+                    && opcode == 0x1260
+                    && address == platform.program_start() =>
+            {
+                Flow::Jump(0x2C0)
+            }
+            0x1000 => Flow::Jump(nnn),
+            0x2000 => Flow::Call(nnn),
+            // 5XY2 and 5XY3 save and load registers, the rest of the row are skips
+            0x5000 if opcode & 0x000F != 0 => Flow::Next,
+            0x3000 | 0x4000 | 0x5000 | 0x9000 | 0xE000 => Flow::Skip,
+            // Returns, exits and computed jumps end the path
+            0x0000 if opcode == 0x00EE || opcode == 0x00FD => Flow::Stop,
+            0xB000 if platform != Platform::Chip8X => Flow::Stop,
+            _ => Flow::Next,
+        }
+    }
+
+    fn classic(opcode: u16, long: u16, platform: Platform) -> Option<String> {
+        let x = (opcode & 0x0F00) >> 8;
+        let y = (opcode & 0x00F0) >> 4;
+        let n = opcode & 0x000F;
+        let nn = opcode & 0x00FF;
+        let nnn = opcode & 0x0FFF;
+
+        let text = match opcode & 0xF000 {
+            0x0000 => match nnn {
+                0x0E0 => "CLS".to_string(),
+                0x0EE => "RET".to_string(),
+                0x0C0..=0x0CF => format!("SCD {}", n),
+                0x0D0..=0x0DF => format!("SCU {}", n),
+                0x0FB => "SCR".to_string(),
+                0x0FC => "SCL".to_string(),
+                0x0FD => "EXIT".to_string(),
+                0x0FE => "LOW".to_string(),
+                0x0FF => "HIGH".to_string(),
+                0x230 if platform == Platform::HiresChip8 => "CLS".to_string(),
+                0x2A0 if platform == Platform::Chip8X => "BGC".to_string(),
+                _ => return None,
+            },
+            0x1000 => format!("JP {:#05X}", nnn),
+            0x2000 => format!("CALL {:#05X}", nnn),
+            0x3000 => format!("SE V{:X}, {:#04X}", x, nn),
+            0x4000 => format!("SNE V{:X}, {:#04X}", x, nn),
+            0x5000 => match n {
+                0x0 => format!("SE V{:X}, V{:X}", x, y),
+                0x2 => format!("LD [I], V{:X}-V{:X}", x, y),
+                0x3 => format!("LD V{:X}-V{:X}, [I]", x, y),
+                _ => return None,
+            },
+            0x6000 => format!("LD V{:X}, {:#04X}", x, nn),
+            0x7000 => format!("ADD V{:X}, {:#04X}", x, nn),
+            0x8000 => match n {
+                0x0 => format!("LD V{:X}, V{:X}", x, y),
+                0x1 => format!("OR V{:X}, V{:X}", x, y),
+                0x2 => format!("AND V{:X}, V{:X}", x, y),
+                0x3 => format!("XOR V{:X}, V{:X}", x, y),
+                0x4 => format!("ADD V{:X}, V{:X}", x, y),
+                0x5 => format!("SUB V{:X}, V{:X}", x, y),
+                0x6 => format!("SHR V{:X}, V{:X}", x, y),
+                0x7 => format!("SUBN V{:X}, V{:X}", x, y),
+                0xE => format!("SHL V{:X}, V{:X}", x, y),
+                _ => return None,
+            },
+            0x9000 if n == 0 => format!("SNE V{:X}, V{:X}", x, y),
+            0xA000 => format!("LD I, {:#05X}", nnn),
+            0xB000 if platform == Platform::Chip8X => format!("COL V{:X}, V{:X}, {}", x, y, n),
+            0xB000 => format!("JP V0, {:#05X}", nnn),
+            0xC000 => format!("RND V{:X}, {:#04X}", x, nn),
+            0xD000 => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+            0xE000 => match nn {
+                0x9E => format!("SKP V{:X}", x),
+                0xA1 => format!("SKNP V{:X}", x),
+                _ => return None,
+            },
+            0xF000 => match nn {
+                0x00 if x == 0 => format!("LD I, LONG {:#06X}", long),
+                0x01 if x < 4 => format!("PLANE {}", x),
+                0x02 if x == 0 => "AUDIO".to_string(),
+                0x07 => format!("LD V{:X}, DT", x),
+                0x0A => format!("LD V{:X}, K", x),
+                0x15 => format!("LD DT, V{:X}", x),
+                0x18 => format!("LD ST, V{:X}", x),
+                0x1E => format!("ADD I, V{:X}", x),
+                0x29 => format!("LD F, V{:X}", x),
+                0x30 => format!("LD HF, V{:X}", x),
+                0x33 => format!("LD B, V{:X}", x),
+                0x3A => format!("PITCH V{:X}", x),
+                0x55 => format!("LD [I], V{:X}", x),
+                0x65 => format!("LD V{:X}, [I]", x),
+                0x75 => format!("LD R, V{:X}", x),
+                0x85 => format!("LD V{:X}, R", x),
+                0xF8 if platform == Platform::Chip8X => format!("OUT V{:X}", x),
+                0xFB if platform == Platform::Chip8X => format!("IN V{:X}", x),
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some(text)
+    }
+
+    // Octo has no syntax for the CHIP-8X and HIRES CHIP-8 extensions, those are left as data
+    fn octo(opcode: u16, long: u16, platform: Platform) -> Option<String> {
+        let x = (opcode & 0x0F00) >> 8;
+        let y = (opcode & 0x00F0) >> 4;
+        let n = opcode & 0x000F;
+        let nn = opcode & 0x00FF;
+        let nnn = opcode & 0x0FFF;
+
+        let text = match opcode & 0xF000 {
+            0x0000 => match nnn {
+                0x0E0 => "clear".to_string(),
+                0x0EE => "return".to_string(),
+                0x0C0..=0x0CF => format!("scroll-down {}", n),
+                0x0D0..=0x0DF => format!("scroll-up {}", n),
+                0x0FB => "scroll-right".to_string(),
+                0x0FC => "scroll-left".to_string(),
+                0x0FD => "exit".to_string(),
+                0x0FE => "lores".to_string(),
+                0x0FF => "hires".to_string(),
+                _ => return None,
+            },
+            0x1000 => format!("jump {:#05X}", nnn),
+            0x2000 => format!(":call {:#05X}", nnn),
+            // Octo conditions name when the next instruction runs, the opposite of the skip
+            0x3000 => format!("if v{:x} != {:#04X} then", x, nn),
+            0x4000 => format!("if v{:x} == {:#04X} then", x, nn),
+            0x5000 => match n {
+                0x0 => format!("if v{:x} != v{:x} then", x, y),
+                0x2 => format!("save v{:x} - v{:x}", x, y),
+                0x3 => format!("load v{:x} - v{:x}", x, y),
+                _ => return None,
+            },
+            0x6000 => format!("v{:x} := {:#04X}", x, nn),
+            0x7000 => format!("v{:x} += {:#04X}", x, nn),
+            0x8000 => match n {
+                0x0 => format!("v{:x} := v{:x}", x, y),
+                0x1 => format!("v{:x} |= v{:x}", x, y),
+                0x2 => format!("v{:x} &= v{:x}", x, y),
+                0x3 => format!("v{:x} ^= v{:x}", x, y),
+                0x4 => format!("v{:x} += v{:x}", x, y),
+                0x5 => format!("v{:x} -= v{:x}", x, y),
+                0x6 => format!("v{:x} >>= v{:x}", x, y),
+                0x7 => format!("v{:x} =- v{:x}", x, y),
+                0xE => format!("v{:x} <<= v{:x}", x, y),
+                _ => return None,
+            },
+            0x9000 if n == 0 => format!("if v{:x} == v{:x} then", x, y),
+            0xA000 => format!("i := {:#05X}", nnn),
+            0xB000 if platform == Platform::Chip8X => return None,
+            0xB000 => format!("jump0 {:#05X}", nnn),
+            0xC000 => format!("v{:x} := random {:#04X}", x, nn),
+            0xD000 => format!("sprite v{:x} v{:x} {}", x, y, n),
+            0xE000 => match nn {
+                0x9E => format!("if v{:x} -key then", x),
+                0xA1 => format!("if v{:x} key then", x),
+                _ => return None,
+            },
+            0xF000 => match nn {
+                0x00 if x == 0 => format!("i := long {:#06X}", long),
+                0x01 if x < 4 => format!("plane {}", x),
+                0x02 if x == 0 => "audio".to_string(),
+                0x07 => format!("v{:x} := delay", x),
+                0x0A => format!("v{:x} := key", x),
+                0x15 => format!("delay := v{:x}", x),
+                0x18 => format!("buzzer := v{:x}", x),
+                0x1E => format!("i += v{:x}", x),
+                0x29 => format!("i := hex v{:x}", x),
+                0x30 => format!("i := bighex v{:x}", x),
+                0x33 => format!("bcd v{:x}", x),
+                0x3A => format!("pitch := v{:x}", x),
+                0x55 => format!("save v{:x}", x),
+                0x65 => format!("load v{:x}", x),
+                0x75 => format!("saveflags v{:x}", x),
+                0x85 => format!("loadflags v{:x}", x),
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some(text)
+    }
+
+    fn classic_data(bytes: &[u8]) -> String {
+        let bytes: Vec<String> = bytes.iter().map(|b| format!("{:#04X}", b)).collect();
+        format!("DB {}", bytes.join(", "))
+    }
+
+    fn octo_data(bytes: &[u8]) -> String {
+        let bytes: Vec<String> = bytes.iter().map(|b| format!("{:#04X}", b)).collect();
+        bytes.join(" ")
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const ROM: [u8; 19] = [
+            0x00, 0xE0, // 200: CLS
+            0xA2, 0x10, // 202: LD I, 0x210
+            0x60, 0x01, // 204: LD V0, 1
+            0x30, 0x01, // 206: SE V0, 1
+            0x22, 0x0C, // 208: CALL 0x20C
+            0x12, 0x0A, // 20A: JP 0x20A
+            0x00, 0xEE, // 20C: RET
+            0x61, 0x05, // 20E: never reached
+            0xF0, 0x90, 0xF0, // 210: sprite
+        ];
+
+        fn listing(platform: Platform, syntax: Syntax) -> Vec<String> {
+            disassemble_rom(&ROM, platform, syntax)
+                .iter()
+                .map(|line| line.to_string())
+                .collect()
+        }
+
+        #[test]
+        fn classic_listing_separates_code_from_data() {
+            assert_eq!(
+                listing(Platform::Chip8, Syntax::Classic),
+                [
+                    "0x200: CLS",
+                    "0x202: LD I, 0x210",
+                    "0x204: LD V0, 0x01",
+                    "0x206: SE V0, 0x01",
+                    "0x208: CALL 0x20C",
+                    "0x20A: JP 0x20A",
+                    "0x20C: RET",
+                    "0x20E: DB 0x61, 0x05, 0xF0, 0x90, 0xF0",
+                ]
+            );
+        }
+
+        #[test]
+        fn octo_listing() {
+            assert_eq!(
+                listing(Platform::Chip8, Syntax::Octo),
+                [
+                    "0x200: clear",
+                    "0x202: i := 0x210",
+                    "0x204: v0 := 0x01",
+                    "0x206: if v0 != 0x01 then",
+                    "0x208: :call 0x20C",
+                    "0x20A: jump 0x20A",
+                    "0x20C: return",
+                    "0x20E: 0x61 0x05 0xF0 0x90 0xF0",
+                ]
+            );
+        }
+
+        #[test]
+        fn long_loads_take_two_words() {
+            let memory = [0xF0, 0x00, 0x12, 0x34];
+            assert_eq!(
+                disassemble(&memory, 0, Platform::Chip8, Syntax::Classic),
+                ("LD I, LONG 0x1234".to_string(), 4)
+            );
+        }
+    }
+}
@@ -0,0 +1,425 @@
+pub mod core {
+    use crate::chip8::{
+        error::chip_error::Chip8Error,
+        movie::movie_file::{framebuffer_hash, Input, Movie},
+        platform::variant::Platform,
+        processor::chip::Chip8,
+        quirks::quirk_profile::Quirks,
+        random::random_source::RandomSource,
+        trace::instruction_trace::Trace,
+    };
+
+    /// Rate the delay and sound timers count down at, and the number of frames per second
+    pub const TIMER_HZ: usize = 60;
+    /// Default CPU clock, in instructions executed per frame
+    pub const DEFAULT_CYCLES_PER_FRAME: usize = 10;
+
+    /// Converts a CPU clock in instructions per second to whole instructions per frame
+    pub const fn ips_to_cycles_per_frame(instructions_per_second: usize) -> usize {
+        let cycles = (instructions_per_second + TIMER_HZ / 2) / TIMER_HZ;
+        if cycles == 0 {
+            1
+        } else {
+            cycles
+        }
+    }
+
+    // A movie being recorded, or replayed along with the keypad state it has reached
+    enum MovieMode {
+        Recording(Movie),
+        Replaying {
+            movie: Movie,
+            next_input: usize,
+            keys: [bool; 16],
+        },
+    }
+
+    /// Embeddable CHIP-8 emulator, independent of any frontend
+    pub struct Emulator {
+        chip8: Chip8,
+        cycles_per_frame: usize,
+        // Instructions run by `step_in_frame` since the timers last ticked
+        cycle: usize,
+        frame: u64,
+        movie: Option<MovieMode>,
+        trace: Option<Trace>,
+    }
+
+    impl Default for Emulator {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl Emulator {
+        pub fn new() -> Emulator {
+            Self::with_quirks(Quirks::default())
+        }
+
+        pub fn with_quirks(quirks: Quirks) -> Emulator {
+            Self::with_platform(quirks, Platform::default())
+        }
+
+        pub fn with_platform(quirks: Quirks, platform: Platform) -> Emulator {
+            Emulator {
+                chip8: Chip8::with_platform(quirks, platform),
+                cycles_per_frame: DEFAULT_CYCLES_PER_FRAME,
+                cycle: 0,
+                frame: 0,
+                movie: None,
+                trace: None,
+            }
+        }
+
+        /// Resets the machine, keeping its quirks, platform and random source, and loads `rom`
+        /// at the platform's program start address
+        pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+            self.chip8.reset();
+            self.cycle = 0;
+            self.frame = 0;
+            self.movie = None;
+            self.chip8.load_rom(rom)
+        }
+
+        /// Resets the machine with `rom` and records a movie of the run from power on
+        pub fn record(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+            self.load_rom(rom)?;
+            // Movies can only reproduce random numbers from a seed
+            let seed = match self.chip8.seed() {
+                Some(seed) => seed,
+                None => {
+                    let seed = rand::random();
+                    self.chip8.reseed(seed);
+                    seed
+                }
+            };
+            self.chip8.log_input(true);
+            self.movie = Some(MovieMode::Recording(Movie::new(
+                rom,
+                seed,
+                self.quirks(),
+                self.platform(),
+                self.cycles_per_frame,
+            )));
+            Ok(())
+        }
+
+        /// Stops recording, returning the movie recorded so far
+        pub fn stop_recording(&mut self) -> Option<Movie> {
+            match self.movie.take() {
+                Some(MovieMode::Recording(movie)) => {
+                    self.chip8.log_input(false);
+                    Some(movie)
+                }
+                other => {
+                    self.movie = other;
+                    None
+                }
+            }
+        }
+
+        /// Resets the machine to the configuration `movie` was recorded with and replays its
+        /// inputs, overriding any keys pressed. Every frame is checked against the framebuffer
+        /// hashes of the movie, and `run_frame` fails at the first one that differs
+        pub fn replay(&mut self, movie: Movie, rom: &[u8]) -> Result<(), Chip8Error> {
+            if !movie.matches_rom(rom) {
+                return Err(Chip8Error::MovieRomMismatch);
+            }
+
+            self.chip8 = Chip8::with_platform(movie.quirks, movie.platform);
+            self.load_rom(rom)?;
+            self.chip8.reseed(movie.seed);
+            self.cycles_per_frame = movie.cycles_per_frame;
+            if movie.frames() > 0 {
+                self.movie = Some(MovieMode::Replaying {
+                    movie,
+                    next_input: 0,
+                    keys: [false; 16],
+                });
+            }
+            Ok(())
+        }
+
+        /// Restarts the random numbers of `CXNN` from `seed`, making runs with the same inputs
+        /// repeat exactly
+        pub fn set_seed(&mut self, seed: u64) {
+            self.chip8.reseed(seed);
+        }
+
+        /// Replaces the source of the random numbers of `CXNN`, such as with a scripted sequence
+        pub fn set_random_source(&mut self, random: Box<dyn RandomSource>) {
+            self.chip8.set_random_source(random);
+        }
+
+        /// Whether a movie is being replayed, false again once all its frames have been verified
+        pub fn is_replaying(&self) -> bool {
+            matches!(self.movie, Some(MovieMode::Replaying { .. }))
+        }
+
+        pub const fn is_recording(&self) -> bool {
+            matches!(self.movie, Some(MovieMode::Recording(_)))
+        }
+
+        /// Number of frames run since the ROM was loaded
+        pub const fn frame(&self) -> u64 {
+            self.frame
+        }
+
+        pub const fn quirks(&self) -> Quirks {
+            self.chip8.quirks()
+        }
+
+        pub const fn platform(&self) -> Platform {
+            self.chip8.platform()
+        }
+
+        /// Executes a single instruction
+        pub fn step(&mut self) -> Result<(), Chip8Error> {
+            if let Some(trace) = &mut self.trace {
+                trace.record(&self.chip8)?;
+            }
+            self.chip8.cycle()
+        }
+
+        /// Traces every instruction executed from now on, or stops tracing with `None`
+        pub fn set_trace(&mut self, trace: Option<Trace>) {
+            self.trace = trace;
+        }
+
+        /// Executes a single instruction, ticking the timers after every frame's worth of them
+        /// so they keep pace when a debugger runs the program an instruction at a time.
+        /// Returns whether the instruction completed a frame
+        pub fn step_in_frame(&mut self) -> Result<bool, Chip8Error> {
+            self.step()?;
+            self.cycle += 1;
+            if self.cycle < self.cycles_per_frame {
+                return Ok(false);
+            }
+            self.cycle = 0;
+            self.chip8.tick_timers();
+            Ok(true)
+        }
+
+        /// Executes one frame (1/60th of a second of emulated time) worth of instructions,
+        /// then ticks the timers once. Stops at the first fault, leaving the timers untouched
+        pub fn run_frame(&mut self) -> Result<(), Chip8Error> {
+            match &mut self.movie {
+                Some(MovieMode::Recording(movie)) => {
+                    for (key, pressed) in self.chip8.take_input_log() {
+                        movie.inputs.push(Input {
+                            frame: self.frame,
+                            key,
+                            pressed,
+                        });
+                    }
+                }
+                Some(MovieMode::Replaying {
+                    movie,
+                    next_input,
+                    keys,
+                }) => {
+                    while let Some(input) = movie
+                        .inputs
+                        .get(*next_input)
+                        .filter(|input| input.frame <= self.frame)
+                    {
+                        keys[input.key as usize] = input.pressed;
+                        *next_input += 1;
+                    }
+                    for (key, &pressed) in keys.iter().enumerate() {
+                        self.chip8.set_action(key as u8, pressed);
+                    }
+                }
+                None => {}
+            }
+
+            for _ in 0..self.cycles_per_frame {
+                self.step()?;
+            }
+            self.chip8.tick_timers();
+
+            let hash = framebuffer_hash(self.chip8.gfx(), self.chip8.width());
+            match &mut self.movie {
+                Some(MovieMode::Recording(movie)) => movie.hashes.push(hash),
+                Some(MovieMode::Replaying { movie, .. }) => {
+                    let expected = movie.hashes[self.frame as usize];
+                    if hash != expected {
+                        return Err(Chip8Error::MovieDesync {
+                            frame: self.frame,
+                            expected,
+                            found: hash,
+                        });
+                    }
+                    if self.frame + 1 == movie.frames() {
+                        self.movie = None;
+                    }
+                }
+                None => {}
+            }
+
+            self.frame += 1;
+            Ok(())
+        }
+
+        pub const fn cycles_per_frame(&self) -> usize {
+            self.cycles_per_frame
+        }
+
+        pub fn set_cycles_per_frame(&mut self, cycles_per_frame: usize) {
+            self.cycles_per_frame = cycles_per_frame;
+        }
+
+        /// Instructions executed per second of emulated time
+        pub const fn clock_speed(&self) -> usize {
+            self.cycles_per_frame * TIMER_HZ
+        }
+
+        /// Sets the CPU clock in instructions per second
+        pub fn set_clock_speed(&mut self, instructions_per_second: usize) {
+            self.cycles_per_frame = ips_to_cycles_per_frame(instructions_per_second);
+        }
+
+        /// The framebuffer at the active resolution, one byte per pixel in row-major order
+        pub fn framebuffer(&self) -> &[u8] {
+            self.chip8.gfx()
+        }
+
+        /// Active display resolution as `(width, height)`, 64x32 unless the platform or SUPER-CHIP
+        /// hi-res changes it
+        pub const fn resolution(&self) -> (usize, usize) {
+            (self.chip8.width(), self.chip8.height())
+        }
+
+        pub fn press_key(&mut self, key: u8) {
+            self.chip8.set_action(key, true);
+        }
+
+        pub fn release_key(&mut self, key: u8) {
+            self.chip8.set_action(key, false);
+        }
+
+        /// Snapshots the machine, see `Chip8::save_state`
+        pub fn save_state(&self) -> Vec<u8> {
+            self.chip8.save_state()
+        }
+
+        /// Restores a snapshot taken with `save_state`
+        pub fn load_state(&mut self, state: &[u8]) -> Result<(), Chip8Error> {
+            self.chip8.load_state(state)
+        }
+
+        pub const fn chip8(&self) -> &Chip8 {
+            &self.chip8
+        }
+
+        pub fn chip8_mut(&mut self) -> &mut Chip8 {
+            &mut self.chip8
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // Draws a pixel at a random position every instruction loop while key 5 is held
+        const ROM: [u8; 19] = [
+            0x62, 0x05, // 200: LD V2, 5
+            0xC0, 0x3F, // 202: RND V0, 0x3F
+            0xC1, 0x1F, // 204: RND V1, 0x1F
+            0xA2, 0x12, // 206: LD I, 0x212
+            0xE2, 0xA1, // 208: SKNP V2
+            0xD0, 0x11, // 20A: DRW V0, V1, 1
+            0x12, 0x02, // 20C: JP 0x202
+            0x00, 0x00, 0x00, 0x00, //
+            0x80, // 212: sprite
+        ];
+
+        // Records 10 frames with key 5 held from frame 3 to frame 6
+        fn recorded_movie() -> Movie {
+            let mut emulator = Emulator::new();
+            emulator.set_seed(42);
+            emulator.record(&ROM).unwrap();
+            for frame in 0..10 {
+                match frame {
+                    3 => emulator.press_key(0x5),
+                    6 => emulator.release_key(0x5),
+                    _ => {}
+                }
+                emulator.run_frame().unwrap();
+            }
+            emulator.stop_recording().unwrap()
+        }
+
+        #[test]
+        fn recording_keeps_the_configuration_inputs_and_hashes() {
+            let movie = recorded_movie();
+            assert_eq!(movie.seed, 42);
+            assert_eq!(movie.quirks, Quirks::default());
+            assert_eq!(movie.cycles_per_frame, DEFAULT_CYCLES_PER_FRAME);
+            assert_eq!(
+                movie.inputs,
+                [
+                    Input {
+                        frame: 3,
+                        key: 0x5,
+                        pressed: true
+                    },
+                    Input {
+                        frame: 6,
+                        key: 0x5,
+                        pressed: false
+                    },
+                ]
+            );
+            assert_eq!(movie.frames(), 10);
+            // Nothing is drawn before the key is pressed
+            assert_eq!(movie.hashes[0], movie.hashes[2]);
+            assert_ne!(movie.hashes[2], movie.hashes[3]);
+        }
+
+        #[test]
+        fn replay_of_a_recording_passes() {
+            let movie: Movie = recorded_movie().to_text().parse().unwrap();
+
+            let mut emulator = Emulator::new();
+            emulator.replay(movie, &ROM).unwrap();
+            for _ in 0..10 {
+                assert!(emulator.is_replaying());
+                emulator.run_frame().unwrap();
+            }
+            assert!(!emulator.is_replaying());
+        }
+
+        #[test]
+        fn replay_stops_at_the_first_differing_frame() {
+            let mut movie = recorded_movie();
+            let expected = movie.hashes[4] ^ 1;
+            movie.hashes[4] = expected;
+            movie.hashes[7] ^= 1;
+
+            let mut emulator = Emulator::new();
+            emulator.replay(movie, &ROM).unwrap();
+            for _ in 0..4 {
+                emulator.run_frame().unwrap();
+            }
+            match emulator.run_frame() {
+                Err(Chip8Error::MovieDesync {
+                    frame,
+                    expected: e,
+                    found,
+                }) => {
+                    assert_eq!((frame, e), (4, expected));
+                    assert_eq!(found, expected ^ 1);
+                }
+                other => panic!("expected a desync, got {:?}", other),
+            }
+        }
+
+        #[test]
+        fn replay_rejects_another_rom() {
+            let mut emulator = Emulator::new();
+            let result = emulator.replay(recorded_movie(), &ROM[..ROM.len() - 1]);
+            assert!(matches!(result, Err(Chip8Error::MovieRomMismatch)));
+        }
+    }
+}
@@ -0,0 +1,97 @@
+pub mod chip_error {
+    use std::{error::Error, fmt, io};
+
+    /// Faults raised while loading or executing a program
+    #[derive(Debug)]
+    pub enum Chip8Error {
+        /// The instruction at `address` is not part of any supported instruction set
+        UnknownOpcode { opcode: u16, address: u16 },
+        /// `2NNN` at `address` was called with all 16 stack levels in use
+        StackOverflow { address: u16 },
+        /// `00EE` at `address` returned with an empty stack
+        StackUnderflow { address: u16 },
+        /// The program counter left addressable memory
+        PcOutOfRange { pc: u16 },
+        /// The instruction at `address` accessed memory past the end using I
+        IrOutOfRange { ir: u16, address: u16 },
+        /// The ROM does not fit in memory after the program start address
+        RomTooLarge { size: usize, max: usize },
+        /// The data is not a save state, or is truncated
+        InvalidState,
+        /// The save state was written by an incompatible version of the format
+        IncompatibleState { version: u16, expected: u16 },
+        /// A replayed movie was recorded with a different ROM
+        MovieRomMismatch,
+        /// The framebuffer after `frame` differs from the one in the replayed movie
+        MovieDesync {
+            frame: u64,
+            expected: u64,
+            found: u64,
+        },
+        /// Reading the ROM or a save state failed
+        Io(io::Error),
+    }
+
+    impl fmt::Display for Chip8Error {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                Chip8Error::UnknownOpcode { opcode, address } => {
+                    write!(f, "Unknown opcode {:#06X} at {:#05X}", opcode, address)
+                }
+                Chip8Error::StackOverflow { address } => {
+                    write!(f, "Stack overflow calling a subroutine at {:#05X}", address)
+                }
+                Chip8Error::StackUnderflow { address } => {
+                    write!(f, "Stack underflow returning from {:#05X}", address)
+                }
+                Chip8Error::PcOutOfRange { pc } => {
+                    write!(f, "Program counter {:#05X} is outside of memory", pc)
+                }
+                Chip8Error::IrOutOfRange { ir, address } => write!(
+                    f,
+                    "Instruction at {:#05X} accessed memory past the end from I = {:#05X}",
+                    address, ir
+                ),
+                Chip8Error::RomTooLarge { size, max } => write!(
+                    f,
+                    "Could Not Load Rom, It's too large ({} bytes, at most {})",
+                    size, max
+                ),
+                Chip8Error::InvalidState => write!(f, "Not a valid save state"),
+                Chip8Error::IncompatibleState { version, expected } => write!(
+                    f,
+                    "Save state version {} is incompatible, expected version {}",
+                    version, expected
+                ),
+                Chip8Error::MovieRomMismatch => {
+                    write!(f, "The movie was recorded with a different ROM")
+                }
+                Chip8Error::MovieDesync {
+                    frame,
+                    expected,
+                    found,
+                } => write!(
+                    f,
+                    "Replay desynced at frame {}, framebuffer hash {:016x} expected {:016x}",
+                    frame, found, expected
+                ),
+                Chip8Error::Io(e) => write!(f, "I/O error: {}", e),
+            }
+        }
+    }
+
+    impl Error for Chip8Error {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            match self {
+                Chip8Error::Io(e) => Some(e),
+                _ => None,
+            }
+        }
+    }
+
+    impl From<io::Error> for Chip8Error {
+        fn from(e: io::Error) -> Self {
+            Chip8Error::Io(e)
+        }
+    }
+}
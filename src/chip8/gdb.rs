@@ -0,0 +1,487 @@
+pub mod gdb_stub {
+    use crate::chip8::{emulator::core::Emulator, error::chip_error::Chip8Error};
+    use std::{
+        collections::BTreeSet,
+        fmt::Write as _,
+        io::{self, ErrorKind, Read, Write},
+        net::{Ipv4Addr, TcpListener, TcpStream},
+    };
+
+    // Register set reported to GDB, in register number order: V0 to VF, I, PC, SP, DT and ST
+    const REGISTER_SIZES: [usize; 21] = [
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 1, 1, 1,
+    ];
+    const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chip8.core">
+    <reg name="v0" bitsize="8" type="uint8"/>
+    <reg name="v1" bitsize="8" type="uint8"/>
+    <reg name="v2" bitsize="8" type="uint8"/>
+    <reg name="v3" bitsize="8" type="uint8"/>
+    <reg name="v4" bitsize="8" type="uint8"/>
+    <reg name="v5" bitsize="8" type="uint8"/>
+    <reg name="v6" bitsize="8" type="uint8"/>
+    <reg name="v7" bitsize="8" type="uint8"/>
+    <reg name="v8" bitsize="8" type="uint8"/>
+    <reg name="v9" bitsize="8" type="uint8"/>
+    <reg name="va" bitsize="8" type="uint8"/>
+    <reg name="vb" bitsize="8" type="uint8"/>
+    <reg name="vc" bitsize="8" type="uint8"/>
+    <reg name="vd" bitsize="8" type="uint8"/>
+    <reg name="ve" bitsize="8" type="uint8"/>
+    <reg name="vf" bitsize="8" type="uint8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+    <reg name="dt" bitsize="8" type="uint8"/>
+    <reg name="st" bitsize="8" type="uint8"/>
+  </feature>
+</target>
+"#;
+
+    // Stop replies for a trap, an interrupt, an illegal instruction and a bad memory access
+    const SIGTRAP: u8 = 5;
+    const SIGINT: u8 = 2;
+    const SIGILL: u8 = 4;
+    const SIGSEGV: u8 = 11;
+
+    const INTERRUPT: u8 = 0x03;
+
+    /// Serves the GDB remote serial protocol to one debugger at a time over TCP on localhost.
+    /// The program runs freely until a debugger attaches, which halts it
+    pub struct GdbServer {
+        listener: TcpListener,
+        client: Option<TcpStream>,
+        input: Vec<u8>,
+        breakpoints: BTreeSet<u16>,
+        halted: bool,
+        killed: bool,
+    }
+
+    impl GdbServer {
+        /// Listens for a debugger on `port` of the loopback interface
+        pub fn bind(port: u16) -> io::Result<GdbServer> {
+            let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+            listener.set_nonblocking(true)?;
+            Ok(GdbServer {
+                listener,
+                client: None,
+                input: Vec::new(),
+                breakpoints: BTreeSet::new(),
+                halted: false,
+                killed: false,
+            })
+        }
+
+        pub fn port(&self) -> io::Result<u16> {
+            Ok(self.listener.local_addr()?.port())
+        }
+
+        /// Whether an attached debugger has stopped the program
+        pub const fn is_halted(&self) -> bool {
+            self.halted
+        }
+
+        /// Whether the debugger asked to kill the program
+        pub const fn is_killed(&self) -> bool {
+            self.killed
+        }
+
+        /// Accepts a waiting debugger and answers the packets it sent, without blocking
+        pub fn poll(&mut self, emulator: &mut Emulator) -> io::Result<()> {
+            if self.client.is_none() {
+                match self.listener.accept() {
+                    Ok((client, address)) => {
+                        println!("GDB attached from {}", address);
+                        client.set_nonblocking(true)?;
+                        client.set_nodelay(true)?;
+                        self.client = Some(client);
+                        self.input.clear();
+                        self.halted = true;
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                    Err(e) => return Err(e),
+                }
+            }
+
+            let mut buffer = [0; 4096];
+            loop {
+                let read = match self.client.as_mut().map(|client| client.read(&mut buffer)) {
+                    Some(Ok(0)) | None => {
+                        self.detach();
+                        return Ok(());
+                    }
+                    Some(Ok(read)) => read,
+                    Some(Err(e)) if e.kind() == ErrorKind::WouldBlock => break,
+                    Some(Err(e)) => {
+                        self.detach();
+                        return Err(e);
+                    }
+                };
+                self.input.extend_from_slice(&buffer[..read]);
+            }
+
+            while let Some(packet) = self.next_packet()? {
+                let reply = self.handle(&packet, emulator);
+                if let Some(reply) = reply {
+                    self.send(&reply)?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Runs the rest of the current frame unless halted, stopping at breakpoints. Faults
+        /// halt the program while a debugger is attached and are returned otherwise
+        pub fn run_frame(&mut self, emulator: &mut Emulator) -> Result<(), Chip8Error> {
+            if self.halted {
+                return Ok(());
+            }
+            if self.client.is_none() {
+                return emulator.run_frame();
+            }
+
+            loop {
+                if self.breakpoints.contains(&emulator.chip8().pc()) {
+                    return Ok(self.stop(SIGTRAP)?);
+                }
+                match emulator.step_in_frame() {
+                    Ok(true) => return Ok(()),
+                    Ok(false) => {}
+                    Err(e) => return Ok(self.stop(signal(&e))?),
+                }
+            }
+        }
+
+        fn stop(&mut self, signal: u8) -> io::Result<()> {
+            self.halted = true;
+            self.send(&format!("S{:02x}", signal))
+        }
+
+        fn detach(&mut self) {
+            if self.client.take().is_some() {
+                println!("GDB detached");
+            }
+            self.breakpoints.clear();
+            self.halted = false;
+        }
+
+        // Takes the next complete packet from the input, acknowledging it. Acknowledgements
+        // from the debugger are skipped and an interrupt halts the program
+        fn next_packet(&mut self) -> io::Result<Option<String>> {
+            loop {
+                match self.input.first() {
+                    None => return Ok(None),
+                    Some(&INTERRUPT) => {
+                        self.input.remove(0);
+                        if !self.halted {
+                            self.stop(SIGINT)?;
+                        }
+                    }
+                    Some(b'$') => break,
+                    Some(_) => {
+                        self.input.remove(0);
+                    }
+                }
+            }
+
+            let end = match self.input.iter().position(|&b| b == b'#') {
+                Some(end) if end + 2 < self.input.len() => end,
+                _ => return Ok(None),
+            };
+            let packet: Vec<u8> = self.input.drain(..end + 3).collect();
+            let data = &packet[1..end];
+            let checksum = std::str::from_utf8(&packet[end + 1..])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+            if checksum != Some(checksum_of(data)) {
+                self.write(b"-")?;
+                return Ok(None);
+            }
+            self.write(b"+")?;
+            Ok(Some(String::from_utf8_lossy(data).to_string()))
+        }
+
+        // Answers a packet, returning nothing while the program runs on after a continue
+        fn handle(&mut self, packet: &str, emulator: &mut Emulator) -> Option<String> {
+            let reply = match packet.as_bytes().first() {
+                Some(b'?') => format!("S{:02x}", SIGTRAP),
+                Some(b'g') => read_registers(emulator),
+                Some(b'G') => {
+                    write_registers(emulator, &packet[1..]).map_or_else(|_| error(), |_| ok())
+                }
+                Some(b'p') => usize::from_str_radix(&packet[1..], 16)
+                    .ok()
+                    .and_then(|register| read_register(emulator, register))
+                    .unwrap_or_else(error),
+                Some(b'P') => packet[1..]
+                    .split_once('=')
+                    .and_then(|(register, value)| {
+                        let register = usize::from_str_radix(register, 16).ok()?;
+                        write_register(emulator, register, &decode_hex(value)?)
+                    })
+                    .map_or_else(error, |_| ok()),
+                Some(b'm') => parse_range(&packet[1..])
+                    .map(|(address, length)| {
+                        let memory = emulator.chip8().memory();
+                        let end = address.saturating_add(length).min(memory.len());
+                        encode_hex(&memory[address.min(end)..end])
+                    })
+                    .unwrap_or_else(error),
+                Some(b'M') => packet[1..]
+                    .split_once(':')
+                    .and_then(|(range, data)| {
+                        let (address, length) = parse_range(range)?;
+                        let data = decode_hex(data).filter(|data| data.len() == length)?;
+                        // Writes reaching past the end of memory fail rather than being cut short
+                        if address.saturating_add(length) > emulator.chip8().memory().len() {
+                            return None;
+                        }
+                        emulator.chip8_mut().write_memory(address, &data);
+                        Some(())
+                    })
+                    .map_or_else(error, |_| ok()),
+                Some(b'Z' | b'z') => self.breakpoint(packet),
+                Some(b's') => {
+                    let signal = match emulator.step_in_frame() {
+                        Ok(_) => SIGTRAP,
+                        Err(e) => signal(&e),
+                    };
+                    format!("S{:02x}", signal)
+                }
+                Some(b'c') => {
+                    self.halted = false;
+                    return None;
+                }
+                Some(b'k') => {
+                    self.killed = true;
+                    self.detach();
+                    return None;
+                }
+                Some(b'D') => {
+                    let _ = self.send(&ok());
+                    self.detach();
+                    return None;
+                }
+                Some(b'H') => ok(),
+                _ if packet.starts_with("qSupported") => {
+                    "PacketSize=4000;qXfer:features:read+".to_string()
+                }
+                _ if packet.starts_with("qXfer:features:read:target.xml:") => {
+                    read_target_xml(&packet["qXfer:features:read:target.xml:".len()..])
+                }
+                _ if packet == "qAttached" => "1".to_string(),
+                _ if packet == "qfThreadInfo" => "m1".to_string(),
+                _ if packet == "qsThreadInfo" => "l".to_string(),
+                _ if packet == "qC" => "QC1".to_string(),
+                // Unsupported packets get an empty reply
+                _ => String::new(),
+            };
+            Some(reply)
+        }
+
+        // Z0 and z0 set and clear software breakpoints, other kinds are unsupported
+        fn breakpoint(&mut self, packet: &str) -> String {
+            let mut fields = packet[1..].split(',');
+            if fields.next() != Some("0") {
+                return String::new();
+            }
+            match fields.next().and_then(|a| u16::from_str_radix(a, 16).ok()) {
+                Some(address) if packet.starts_with('Z') => {
+                    self.breakpoints.insert(address);
+                    ok()
+                }
+                Some(address) => {
+                    self.breakpoints.remove(&address);
+                    ok()
+                }
+                None => error(),
+            }
+        }
+
+        fn send(&mut self, data: &str) -> io::Result<()> {
+            let packet = format!("${}#{:02x}", data, checksum_of(data.as_bytes()));
+            self.write(packet.as_bytes())
+        }
+
+        fn write(&mut self, data: &[u8]) -> io::Result<()> {
+            let client = match self.client.as_mut() {
+                Some(client) => client,
+                None => return Ok(()),
+            };
+
+            // The socket is non-blocking, keep trying until the whole reply is out
+            let mut written = 0;
+            while written < data.len() {
+                match client.write(&data[written..]) {
+                    Ok(n) => written += n,
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::yield_now(),
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn signal(e: &Chip8Error) -> u8 {
+        match e {
+            Chip8Error::UnknownOpcode { .. } => SIGILL,
+            _ => SIGSEGV,
+        }
+    }
+
+    fn ok() -> String {
+        "OK".to_string()
+    }
+
+    fn error() -> String {
+        "E01".to_string()
+    }
+
+    fn checksum_of(data: &[u8]) -> u8 {
+        data.iter().fold(0, |sum, &b| sum.wrapping_add(b))
+    }
+
+    fn encode_hex(data: &[u8]) -> String {
+        data.iter().fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+    }
+
+    fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+        if hex.len() % 2 != 0 {
+            return None;
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
+
+    // Parses `addr,length` in hex
+    fn parse_range(range: &str) -> Option<(usize, usize)> {
+        let (address, length) = range.split_once(',')?;
+        Some((
+            usize::from_str_radix(address, 16).ok()?,
+            usize::from_str_radix(length, 16).ok()?,
+        ))
+    }
+
+    // Register values in target byte order, little endian
+    fn register_value(emulator: &Emulator, register: usize) -> Option<u16> {
+        let chip8 = emulator.chip8();
+        match register {
+            0..=15 => Some(chip8.registers()[register] as u16),
+            16 => Some(chip8.ir()),
+            17 => Some(chip8.pc()),
+            18 => Some(chip8.sp()),
+            19 => Some(chip8.delay_timer() as u16),
+            20 => Some(chip8.sound_timer() as u16),
+            _ => None,
+        }
+    }
+
+    fn read_register(emulator: &Emulator, register: usize) -> Option<String> {
+        let value = register_value(emulator, register)?;
+        Some(encode_hex(&value.to_le_bytes()[..REGISTER_SIZES[register]]))
+    }
+
+    fn read_registers(emulator: &Emulator) -> String {
+        (0..REGISTER_SIZES.len())
+            .filter_map(|register| read_register(emulator, register))
+            .collect()
+    }
+
+    fn write_register(emulator: &mut Emulator, register: usize, bytes: &[u8]) -> Option<()> {
+        if bytes.len() != *REGISTER_SIZES.get(register)? {
+            return None;
+        }
+        let value = bytes[0] as u16 | (*bytes.get(1).unwrap_or(&0) as u16) << 8;
+
+        let chip8 = emulator.chip8_mut();
+        match register {
+            0..=15 => chip8.set_register(register, value as u8),
+            16 => chip8.set_ir(value),
+            17 => chip8.set_pc(value),
+            18 => chip8.set_sp(value),
+            19 => chip8.set_delay_timer(value as u8),
+            _ => chip8.set_sound_timer(value as u8),
+        }
+        Some(())
+    }
+
+    fn write_registers(emulator: &mut Emulator, hex: &str) -> Result<(), ()> {
+        let bytes = decode_hex(hex).ok_or(())?;
+        if bytes.len() != REGISTER_SIZES.iter().sum::<usize>() {
+            return Err(());
+        }
+
+        let mut offset = 0;
+        for (register, &size) in REGISTER_SIZES.iter().enumerate() {
+            write_register(emulator, register, &bytes[offset..offset + size]).ok_or(())?;
+            offset += size;
+        }
+        Ok(())
+    }
+
+    // Serves the `offset,length` window of the target description
+    fn read_target_xml(window: &str) -> String {
+        let (offset, length) = match parse_range(window) {
+            Some(range) => range,
+            None => return error(),
+        };
+        let start = offset.min(TARGET_XML.len());
+        let end = offset.saturating_add(length).min(TARGET_XML.len());
+        let prefix = if end == TARGET_XML.len() { 'l' } else { 'm' };
+        format!("{}{}", prefix, &TARGET_XML[start..end])
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn server() -> GdbServer {
+            GdbServer::bind(0).unwrap()
+        }
+
+        #[test]
+        fn reads_stop_at_the_end_of_memory() {
+            let mut emulator = Emulator::new();
+            let mut gdb = server();
+
+            let reply = gdb.handle("mffff,2", &mut emulator);
+            assert_eq!(reply.as_deref(), Some("00"));
+            let reply = gdb.handle("mffffffffffffffff,1", &mut emulator);
+            assert_eq!(reply.as_deref(), Some(""));
+            let reply = gdb.handle("mfffe,ffffffffffffffff", &mut emulator);
+            assert_eq!(reply.as_deref(), Some("0000"));
+        }
+
+        #[test]
+        fn writes_past_the_end_of_memory_fail() {
+            let mut emulator = Emulator::new();
+            let mut gdb = server();
+
+            let reply = gdb.handle("Mfffe,2:1234", &mut emulator);
+            assert_eq!(reply.as_deref(), Some("OK"));
+            assert_eq!(&emulator.chip8().memory()[0xFFFE..], &[0x12, 0x34]);
+
+            let reply = gdb.handle("Mffff,2:5678", &mut emulator);
+            assert_eq!(reply.as_deref(), Some("E01"));
+            assert_eq!(emulator.chip8().memory()[0xFFFF], 0x34);
+            let reply = gdb.handle("Mffffffffffffffff,1:00", &mut emulator);
+            assert_eq!(reply.as_deref(), Some("E01"));
+        }
+
+        #[test]
+        fn target_xml_windows_are_clamped() {
+            assert_eq!(read_target_xml("ffffffffffffffff,10"), "l");
+            assert_eq!(
+                read_target_xml("0,ffffffffffffffff"),
+                format!("l{}", TARGET_XML)
+            );
+        }
+    }
+}
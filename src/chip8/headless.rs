@@ -0,0 +1,183 @@
+pub mod headless_runner {
+    use crate::chip8::{emulator::core::Emulator, error::chip_error::Chip8Error};
+    use std::{fmt, str::FromStr};
+
+    /// Frames a scripted key is held for when no duration is given
+    pub const DEFAULT_KEY_FRAMES: u64 = 5;
+
+    /// A key held down for a number of frames, written `FRAME:KEY[:FRAMES]` with the key as a
+    /// hex digit
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct KeyPress {
+        pub frame: u64,
+        pub key: u8,
+        pub frames: u64,
+    }
+
+    impl KeyPress {
+        // Frame the key is released on
+        const fn release(&self) -> u64 {
+            self.frame + self.frames
+        }
+    }
+
+    impl FromStr for KeyPress {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let invalid = || format!("Invalid key press '{}', expected FRAME:KEY[:FRAMES]", s);
+            let mut fields = s.split(':');
+
+            let frame = fields
+                .next()
+                .and_then(|f| f.parse().ok())
+                .ok_or_else(invalid)?;
+            let key = fields
+                .next()
+                .and_then(|k| u8::from_str_radix(k, 16).ok())
+                .filter(|&k| k < 16)
+                .ok_or_else(invalid)?;
+            let frames = match fields.next() {
+                Some(f) => f.parse().ok().filter(|&f| f > 0).ok_or_else(invalid)?,
+                None => DEFAULT_KEY_FRAMES,
+            };
+            if fields.next().is_some() {
+                return Err(invalid());
+            }
+
+            Ok(KeyPress { frame, key, frames })
+        }
+    }
+
+    /// Why a headless run stopped
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Halt {
+        /// Every requested frame ran
+        FrameLimit,
+        /// The program exited with `00FD`
+        Exited,
+        /// The program is stuck on a jump to itself with no scripted keys left
+        Looping,
+    }
+
+    impl fmt::Display for Halt {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Halt::FrameLimit => write!(f, "frame limit reached"),
+                Halt::Exited => write!(f, "program exited"),
+                Halt::Looping => write!(f, "program halted in a loop"),
+            }
+        }
+    }
+
+    /// Runs up to `frames` frames of a loaded program without a window, pressing and releasing
+    /// keys as scripted. Stops early once the program exits, or once it spins on a `1NNN` jump
+    /// to itself after the last scripted key is released
+    pub fn run_headless(
+        emulator: &mut Emulator,
+        frames: u64,
+        presses: &[KeyPress],
+    ) -> Result<Halt, Chip8Error> {
+        for frame in 0..frames {
+            for press in presses {
+                if press.frame == frame {
+                    emulator.press_key(press.key);
+                } else if press.release() == frame {
+                    emulator.release_key(press.key);
+                }
+            }
+
+            if emulator.chip8().has_exited() {
+                return Ok(Halt::Exited);
+            }
+            let scripted = presses.iter().any(|press| press.release() >= frame);
+            if !scripted && is_looping(emulator) {
+                return Ok(Halt::Looping);
+            }
+
+            emulator.run_frame()?;
+        }
+
+        Ok(if emulator.chip8().has_exited() {
+            Halt::Exited
+        } else {
+            Halt::FrameLimit
+        })
+    }
+
+    // Whether the instruction at PC jumps to itself
+    fn is_looping(emulator: &Emulator) -> bool {
+        let chip8 = emulator.chip8();
+        let pc = chip8.pc() as usize;
+        match chip8.memory().get(pc..pc + 2) {
+            Some(&[high, low]) if pc < 0x1000 => {
+                u16::from_be_bytes([high, low]) == 0x1000 | pc as u16
+            }
+            _ => false,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn run(program: &[u8], frames: u64, presses: &[KeyPress]) -> (Halt, u64) {
+            let mut emulator = Emulator::new();
+            emulator.load_rom(program).unwrap();
+            let halt = run_headless(&mut emulator, frames, presses).unwrap();
+            (halt, emulator.frame())
+        }
+
+        #[test]
+        fn key_presses_parse() {
+            assert_eq!(
+                "10:A".parse(),
+                Ok(KeyPress {
+                    frame: 10,
+                    key: 0xA,
+                    frames: DEFAULT_KEY_FRAMES
+                })
+            );
+            assert_eq!(
+                "0:f:2".parse(),
+                Ok(KeyPress {
+                    frame: 0,
+                    key: 0xF,
+                    frames: 2
+                })
+            );
+            for invalid in ["", "1", "1:10", "1:A:0", "1:A:2:3", "x:A"] {
+                assert!(invalid.parse::<KeyPress>().is_err(), "{}", invalid);
+            }
+        }
+
+        #[test]
+        fn runs_to_the_frame_limit() {
+            // ADD V0, 1; JP 0x200
+            assert_eq!(
+                run(&[0x70, 0x01, 0x12, 0x00], 3, &[]),
+                (Halt::FrameLimit, 3)
+            );
+        }
+
+        #[test]
+        fn stops_when_the_program_exits() {
+            // CLS; EXIT
+            assert_eq!(run(&[0x00, 0xE0, 0x00, 0xFD], 10, &[]), (Halt::Exited, 1));
+        }
+
+        #[test]
+        fn stops_in_a_jump_to_itself_once_keys_are_released() {
+            // JP 0x200
+            let program = [0x12, 0x00];
+            assert_eq!(run(&program, 10, &[]), (Halt::Looping, 0));
+
+            let press = KeyPress {
+                frame: 2,
+                key: 0x5,
+                frames: 3,
+            };
+            assert_eq!(run(&program, 10, &[press]), (Halt::Looping, 6));
+        }
+    }
+}
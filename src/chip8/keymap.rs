@@ -0,0 +1,214 @@
+pub mod key_bindings {
+    use sdl2::{
+        controller::Button,
+        keyboard::{Keycode, Scancode},
+    };
+    use std::str::FromStr;
+
+    /// Hex keys in the order of the COSMAC VIP keypad, row by row
+    pub const KEYPAD_LAYOUT: [u8; 16] = [
+        0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+    ];
+
+    // Physical keys of the default mapping, indexed by hex key, so the 1234 / QWER / ASDF / ZXCV
+    // block stands in for the keypad whatever the keyboard layout
+    const DEFAULT_SCANCODES: [Scancode; 16] = [
+        Scancode::X,
+        Scancode::Num1,
+        Scancode::Num2,
+        Scancode::Num3,
+        Scancode::Q,
+        Scancode::W,
+        Scancode::E,
+        Scancode::A,
+        Scancode::S,
+        Scancode::D,
+        Scancode::Z,
+        Scancode::C,
+        Scancode::Num4,
+        Scancode::R,
+        Scancode::F,
+        Scancode::V,
+    ];
+
+    // Controller buttons of the default mapping, the d-pad on the 5 / 7 / 8 / 9 movement keys
+    // most ROMs use and the face buttons on the keys next to them
+    const DEFAULT_BUTTONS: [(Button, u8); 8] = [
+        (Button::DPadUp, 0x5),
+        (Button::DPadLeft, 0x7),
+        (Button::DPadDown, 0x8),
+        (Button::DPadRight, 0x9),
+        (Button::A, 0x6),
+        (Button::B, 0x4),
+        (Button::X, 0xA),
+        (Button::Y, 0xB),
+    ];
+
+    /// A key on the host keyboard, either a physical position or the symbol printed on it, or
+    /// a game controller button
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum HostKey {
+        Scancode(Scancode),
+        Keycode(Keycode),
+        Button(Button),
+    }
+
+    impl HostKey {
+        fn name(&self) -> String {
+            match self {
+                HostKey::Scancode(scancode) => scancode.name().to_string(),
+                HostKey::Keycode(keycode) => format!("key:{}", keycode.name()),
+                HostKey::Button(button) => format!("pad:{}", button.string()),
+            }
+        }
+    }
+
+    impl FromStr for HostKey {
+        type Err = String;
+
+        /// SDL key names are physical keys by default, with a `key:` prefix they follow the
+        /// keyboard layout, and `pad:` names an SDL game controller button such as `pad:dpup`
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            if let Some(name) = s.strip_prefix("key:") {
+                Keycode::from_name(name).map(HostKey::Keycode)
+            } else if let Some(name) = s.strip_prefix("pad:") {
+                Button::from_string(name).map(HostKey::Button)
+            } else {
+                Scancode::from_name(s).map(HostKey::Scancode)
+            }
+            .ok_or_else(|| format!("Unknown key \"{}\"", s))
+        }
+    }
+
+    /// Host keys bound to each of the 16 hex keys, any number per hex key
+    ///
+    /// Keymap files bind one hex key per line, replacing its default keys. Lines starting with
+    /// `#` are comments
+    ///
+    /// ```text
+    /// # Arrow keys as well as WASD for the usual movement keys
+    /// 5 = W, Up
+    /// 7 = A, Left
+    /// 8 = S, Down
+    /// 9 = D, Right
+    /// # The key labelled Q, wherever the layout puts it
+    /// 4 = key:Q
+    /// # Controller buttons go alongside the keys
+    /// 6 = E, pad:a, pad:rightshoulder
+    /// ```
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Keymap {
+        keys: [Vec<HostKey>; 16],
+    }
+
+    impl Default for Keymap {
+        fn default() -> Self {
+            let mut keymap = Keymap {
+                keys: DEFAULT_SCANCODES.map(|scancode| vec![HostKey::Scancode(scancode)]),
+            };
+            for (button, key) in DEFAULT_BUTTONS {
+                keymap.keys[key as usize].push(HostKey::Button(button));
+            }
+            keymap
+        }
+    }
+
+    impl Keymap {
+        /// Host keys bound to the hex key `key`
+        pub fn host_keys(&self, key: u8) -> &[HostKey] {
+            &self.keys[key as usize & 0xF]
+        }
+
+        /// Binds `host_keys` to the hex key `key`, replacing its current keys
+        pub fn bind(&mut self, key: u8, host_keys: Vec<HostKey>) {
+            self.keys[key as usize & 0xF] = host_keys;
+        }
+
+        /// Moves the d-pad and the A and B buttons onto the hex keys a ROM database lists for
+        /// `up`, `down`, `left`, `right`, `a` and `b`
+        pub fn apply_hints(&mut self, hints: &[(String, u8)]) {
+            for (name, key) in hints {
+                let button = match name.as_str() {
+                    "up" => Button::DPadUp,
+                    "down" => Button::DPadDown,
+                    "left" => Button::DPadLeft,
+                    "right" => Button::DPadRight,
+                    "a" => Button::A,
+                    "b" => Button::B,
+                    _ => continue,
+                };
+                for host_keys in &mut self.keys {
+                    host_keys.retain(|&host_key| host_key != HostKey::Button(button));
+                }
+                self.keys[*key as usize & 0xF].push(HostKey::Button(button));
+            }
+        }
+
+        /// Hex keys `host_key` is bound to
+        pub fn lookup(&self, host_key: HostKey) -> impl Iterator<Item = u8> + '_ {
+            self.keys
+                .iter()
+                .enumerate()
+                .filter(move |(_, host_keys)| host_keys.contains(&host_key))
+                .map(|(key, _)| key as u8)
+        }
+
+        /// The bindings laid out like the COSMAC VIP keypad, one row per line
+        pub fn legend(&self) -> String {
+            let cells: Vec<String> = KEYPAD_LAYOUT
+                .iter()
+                .map(|&key| {
+                    let names: Vec<String> =
+                        self.host_keys(key).iter().map(HostKey::name).collect();
+                    format!("{:X}: {}", key, names.join("/"))
+                })
+                .collect();
+            let width = cells.iter().map(String::len).max().unwrap_or_default();
+
+            cells
+                .chunks(4)
+                .map(|row| {
+                    let row: Vec<String> = row.iter().map(|c| format!("{:width$}", c)).collect();
+                    row.join("  ").trim_end().to_string()
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        }
+    }
+
+    impl FromStr for Keymap {
+        type Err = String;
+
+        /// Parses a keymap file, hex keys it does not mention keep their default keys
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let mut keymap = Keymap::default();
+
+            for (number, line) in s.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                let error = |message: String| format!("Line {}: {}", number + 1, message);
+                let (key, host_keys) = line
+                    .split_once('=')
+                    .ok_or_else(|| error("expected KEY = HOST_KEY, ...".to_string()))?;
+                let key = u8::from_str_radix(key.trim(), 16)
+                    .ok()
+                    .filter(|&key| key < 16)
+                    .ok_or_else(|| error(format!("\"{}\" is not a hex key", key.trim())))?;
+                // A key with nothing after the = is left unbound
+                let host_keys = host_keys
+                    .split(',')
+                    .filter(|name| !name.trim().is_empty())
+                    .map(|name| name.trim().parse())
+                    .collect::<Result<Vec<HostKey>, String>>()
+                    .map_err(error)?;
+
+                keymap.bind(key, host_keys);
+            }
+
+            Ok(keymap)
+        }
+    }
+}
@@ -1,3 +1,24 @@
+pub mod assembler;
+#[cfg(feature = "sdl")]
 pub mod audio;
+pub mod database;
+pub mod debugger;
+pub mod disassembler;
+pub mod emulator;
+pub mod error;
+pub mod gdb;
+pub mod headless;
+#[cfg(feature = "sdl")]
+pub mod keymap;
+pub mod movie;
+pub mod platform;
 pub mod processor;
+pub mod quirks;
+pub mod random;
+pub mod rewind;
+pub mod screen;
+pub mod state;
+pub mod theme;
+pub mod trace;
+#[cfg(feature = "sdl")]
 pub mod window;
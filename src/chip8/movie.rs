@@ -0,0 +1,238 @@
+pub mod movie_file {
+    use crate::chip8::{platform::variant::Platform, quirks::quirk_profile::Quirks};
+    use std::{fmt::Write, str::FromStr};
+
+    /// First line of every movie file, followed by the format version
+    pub const MAGIC: &str = "CHIP8-MOVIE";
+    /// Movie format version, bumped whenever the layout changes
+    pub const VERSION: u32 = 1;
+
+    /// A keypad state change, made before the frame `frame` ran
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct Input {
+        pub frame: u64,
+        pub key: u8,
+        pub pressed: bool,
+    }
+
+    /// Everything needed to reproduce a run: the machine configuration, the inputs keyed to
+    /// emulated frame numbers and a hash of the framebuffer after every frame.
+    ///
+    /// Stored as text, one item per line
+    ///
+    /// ```text
+    /// CHIP8-MOVIE 1
+    /// rom <length> <fnv-1a hash>
+    /// seed <seed>
+    /// quirks <bits>
+    /// platform <id>
+    /// cycles-per-frame <count>
+    /// input <frame> <key> down|up
+    /// hash <frame> <fnv-1a hash>
+    /// ```
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Movie {
+        pub rom_length: usize,
+        pub rom_hash: u64,
+        pub seed: u64,
+        pub quirks: Quirks,
+        pub platform: Platform,
+        pub cycles_per_frame: usize,
+        pub inputs: Vec<Input>,
+        pub hashes: Vec<u64>,
+    }
+
+    /// 64 bit FNV-1a hash, used for the ROM and for framebuffers
+    pub fn fnv1a(data: &[u8]) -> u64 {
+        data.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+        })
+    }
+
+    /// Hash of a framebuffer, including its width so resolution changes are told apart
+    pub fn framebuffer_hash(gfx: &[u8], width: usize) -> u64 {
+        fnv1a(&[&(width as u32).to_le_bytes(), gfx].concat())
+    }
+
+    impl Movie {
+        pub fn new(
+            rom: &[u8],
+            seed: u64,
+            quirks: Quirks,
+            platform: Platform,
+            cycles_per_frame: usize,
+        ) -> Movie {
+            Movie {
+                rom_length: rom.len(),
+                rom_hash: fnv1a(rom),
+                seed,
+                quirks,
+                platform,
+                cycles_per_frame,
+                inputs: Vec::new(),
+                hashes: Vec::new(),
+            }
+        }
+
+        /// Number of frames recorded
+        pub fn frames(&self) -> u64 {
+            self.hashes.len() as u64
+        }
+
+        /// Whether the movie was recorded with `rom`
+        pub fn matches_rom(&self, rom: &[u8]) -> bool {
+            self.rom_length == rom.len() && self.rom_hash == fnv1a(rom)
+        }
+
+        pub fn to_text(&self) -> String {
+            let mut text = String::new();
+            let _ = writeln!(text, "{} {}", MAGIC, VERSION);
+            let _ = writeln!(text, "rom {} {:016x}", self.rom_length, self.rom_hash);
+            let _ = writeln!(text, "seed {}", self.seed);
+            let _ = writeln!(text, "quirks {}", self.quirks.to_bits());
+            let _ = writeln!(text, "platform {}", self.platform.id());
+            let _ = writeln!(text, "cycles-per-frame {}", self.cycles_per_frame);
+
+            // Inputs and hashes are interleaved in frame order
+            let mut inputs = self.inputs.iter().peekable();
+            for (frame, hash) in self.hashes.iter().enumerate() {
+                while let Some(input) = inputs.next_if(|input| input.frame <= frame as u64) {
+                    let state = if input.pressed { "down" } else { "up" };
+                    let _ = writeln!(text, "input {} {:X} {}", input.frame, input.key, state);
+                }
+                let _ = writeln!(text, "hash {} {:016x}", frame, hash);
+            }
+
+            text
+        }
+    }
+
+    impl FromStr for Movie {
+        type Err = String;
+
+        fn from_str(text: &str) -> Result<Self, Self::Err> {
+            let mut lines = text.lines().enumerate();
+
+            match lines
+                .next()
+                .map(|(_, line)| line.split_whitespace().collect::<Vec<_>>())
+            {
+                Some(header) if header == [MAGIC, VERSION.to_string().as_str()] => {}
+                Some(header) if header.first() == Some(&MAGIC) => {
+                    return Err(format!(
+                        "Movie version {} is incompatible, expected version {}",
+                        header.get(1).unwrap_or(&"?"),
+                        VERSION
+                    ))
+                }
+                _ => return Err("Not a movie file".to_string()),
+            }
+
+            let mut movie = Movie::new(&[], 0, Quirks::default(), Platform::default(), 1);
+
+            for (number, line) in lines {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let invalid = || format!("Invalid movie line {}: \"{}\"", number + 1, line);
+                let number_at = |i: usize| -> Result<u64, String> {
+                    fields
+                        .get(i)
+                        .and_then(|f| f.parse().ok())
+                        .ok_or_else(invalid)
+                };
+                let hex_at = |i: usize| -> Result<u64, String> {
+                    fields
+                        .get(i)
+                        .and_then(|f| u64::from_str_radix(f, 16).ok())
+                        .ok_or_else(invalid)
+                };
+
+                match fields.first() {
+                    None => {}
+                    Some(&"rom") => {
+                        movie.rom_length = number_at(1)? as usize;
+                        movie.rom_hash = hex_at(2)?;
+                    }
+                    Some(&"seed") => movie.seed = number_at(1)?,
+                    Some(&"quirks") => movie.quirks = Quirks::from_bits(number_at(1)? as u8),
+                    Some(&"platform") => {
+                        movie.platform =
+                            Platform::from_id(number_at(1)? as u8).ok_or_else(invalid)?;
+                    }
+                    Some(&"cycles-per-frame") => movie.cycles_per_frame = number_at(1)? as usize,
+                    Some(&"input") => movie.inputs.push(Input {
+                        frame: number_at(1)?,
+                        key: hex_at(2)? as u8 & 0xF,
+                        pressed: match fields.get(3) {
+                            Some(&"down") => true,
+                            Some(&"up") => false,
+                            _ => return Err(invalid()),
+                        },
+                    }),
+                    Some(&"hash") => {
+                        if number_at(1)? != movie.frames() {
+                            return Err(invalid());
+                        }
+                        movie.hashes.push(hex_at(2)?);
+                    }
+                    Some(_) => return Err(invalid()),
+                }
+            }
+
+            Ok(movie)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn text_round_trips() {
+            let mut movie = Movie::new(
+                &[0x12, 0x00],
+                1234,
+                Quirks::SUPER_CHIP,
+                Platform::Chip8X,
+                15,
+            );
+            movie.inputs = vec![
+                Input {
+                    frame: 0,
+                    key: 0x5,
+                    pressed: true,
+                },
+                Input {
+                    frame: 2,
+                    key: 0x5,
+                    pressed: false,
+                },
+                Input {
+                    frame: 2,
+                    key: 0xF,
+                    pressed: true,
+                },
+            ];
+            movie.hashes = vec![0x1, 0xFFFF_FFFF_FFFF_FFFF, 0x0123_4567_89AB_CDEF];
+
+            let text = movie.to_text();
+            assert!(text.starts_with("CHIP8-MOVIE 1\n"));
+            assert_eq!(text.parse::<Movie>(), Ok(movie));
+        }
+
+        #[test]
+        fn other_versions_and_files_are_rejected() {
+            let error = "CHIP8-MOVIE 2\n".parse::<Movie>().unwrap_err();
+            assert_eq!(error, "Movie version 2 is incompatible, expected version 1");
+            assert_eq!("CH8S".parse::<Movie>(), Err("Not a movie file".to_string()));
+        }
+
+        #[test]
+        fn hashes_out_of_frame_order_are_rejected() {
+            let text = "CHIP8-MOVIE 1\nhash 0 1\nhash 2 2\n";
+            assert_eq!(
+                text.parse::<Movie>(),
+                Err("Invalid movie line 3: \"hash 2 2\"".to_string())
+            );
+        }
+    }
+}
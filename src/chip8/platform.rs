@@ -0,0 +1,139 @@
+pub mod variant {
+    use std::str::FromStr;
+
+    /// Historical interpreter a ROM was written for, deciding where it is loaded, the display
+    /// geometry it starts with and the extra instructions decoded
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub enum Platform {
+        /// CHIP-8 with the SUPER-CHIP and XO-CHIP extensions
+        #[default]
+        Chip8,
+        /// The 64x64 "HIRES" CHIP-8, entering through a `1260` trampoline to 0x2C0
+        HiresChip8,
+        /// CHIP-8X for the VP-590 colour board, with colour zones and I/O instructions
+        Chip8X,
+        /// The ETI-660 interpreter, with programs at 0x600 and a 64x48 display
+        Eti660,
+    }
+
+    impl Platform {
+        /// Address programs are loaded at and start executing from
+        pub const fn program_start(self) -> usize {
+            match self {
+                Platform::Chip8 | Platform::HiresChip8 => 0x200,
+                Platform::Chip8X => 0x300,
+                Platform::Eti660 => 0x600,
+            }
+        }
+
+        /// Stable numeric identifier, used in save states
+        pub const fn id(self) -> u8 {
+            match self {
+                Platform::Chip8 => 0,
+                Platform::HiresChip8 => 1,
+                Platform::Chip8X => 2,
+                Platform::Eti660 => 3,
+            }
+        }
+
+        pub const fn from_id(id: u8) -> Option<Platform> {
+            match id {
+                0 => Some(Platform::Chip8),
+                1 => Some(Platform::HiresChip8),
+                2 => Some(Platform::Chip8X),
+                3 => Some(Platform::Eti660),
+                _ => None,
+            }
+        }
+
+        /// Display resolution as `(width, height)` when the program starts
+        pub const fn resolution(self) -> (usize, usize) {
+            match self {
+                Platform::Chip8 | Platform::Chip8X => (64, 32),
+                Platform::HiresChip8 => (64, 64),
+                Platform::Eti660 => (64, 48),
+            }
+        }
+    }
+
+    impl FromStr for Platform {
+        type Err = String;
+
+        fn from_str(name: &str) -> Result<Self, Self::Err> {
+            match name.to_ascii_lowercase().as_str() {
+                "chip8" | "chip-8" => Ok(Platform::Chip8),
+                "hires" | "hires-chip8" | "chip8-hires" => Ok(Platform::HiresChip8),
+                "chip8x" | "chip-8x" => Ok(Platform::Chip8X),
+                "eti660" | "eti-660" => Ok(Platform::Eti660),
+                _ => Err(format!(
+                    "Unknown platform \"{}\", expected chip8, hires, chip8x or eti660",
+                    name
+                )),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::chip8::{processor::chip::Chip8, quirks::quirk_profile::Quirks};
+
+        #[test]
+        fn platforms_start_at_their_entry_point_and_resolution() {
+            let expected = [
+                (Platform::Chip8, 0x200, (64, 32)),
+                (Platform::HiresChip8, 0x200, (64, 64)),
+                (Platform::Chip8X, 0x300, (64, 32)),
+                (Platform::Eti660, 0x600, (64, 48)),
+            ];
+            for (platform, start, (width, height)) in expected {
+                let mut chip8 = Chip8::with_platform(Quirks::default(), platform);
+                chip8.load_rom(&[0x60, 0x2A]).unwrap();
+
+                assert_eq!(chip8.pc() as usize, start, "{:?}", platform);
+                assert_eq!((chip8.width(), chip8.height()), (width, height));
+                assert_eq!(chip8.memory()[start..start + 2], [0x60, 0x2A]);
+                chip8.cycle().unwrap();
+                assert_eq!(chip8.registers()[0], 0x2A, "{:?}", platform);
+                assert_eq!(Platform::from_id(platform.id()), Some(platform));
+            }
+        }
+
+        #[test]
+        fn hires_trampoline_enters_at_0x2c0() {
+            let mut chip8 = Chip8::with_platform(Quirks::default(), Platform::HiresChip8);
+            chip8.load_rom(&[0x12, 0x60]).unwrap();
+            chip8.cycle().unwrap();
+            assert_eq!(chip8.pc(), 0x2C0);
+
+            // Elsewhere 1260 is an ordinary jump
+            let mut chip8 = Chip8::new(Quirks::default());
+            chip8.load_rom(&[0x12, 0x60]).unwrap();
+            chip8.cycle().unwrap();
+            assert_eq!(chip8.pc(), 0x260);
+        }
+
+        #[test]
+        fn chip8x_colours_zones_and_background() {
+            let program = [
+                0x60, 0x10, // LD V0, 0x10, zone columns 0 and 1
+                0x61, 0x00, // LD V1, 0x00, the first row of zones
+                0x62, 0x02, // LD V2, 2
+                0xB0, 0x20, // COL V0, V2, 0
+                0x02, 0xA0, // BGC
+            ];
+            let mut chip8 = Chip8::with_platform(Quirks::default(), Platform::Chip8X);
+            chip8.load_rom(&program).unwrap();
+            let default = chip8.zone_colour(0, 0);
+            for _ in 0..5 {
+                chip8.cycle().unwrap();
+            }
+
+            assert_eq!(chip8.zone_colour(0, 0), 2);
+            assert_eq!(chip8.zone_colour(15, 3), 2);
+            assert_eq!(chip8.zone_colour(16, 0), default);
+            assert_eq!(chip8.zone_colour(0, 4), default);
+            assert_eq!(chip8.background_colour(), 1);
+        }
+    }
+}
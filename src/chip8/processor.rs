@@ -1,9 +1,32 @@
 pub mod chip {
-    use rand::Rng;
-    use sdl2::{keyboard::Keycode, rect::Rect, render::Canvas, video::Window};
+    use crate::chip8::{
+        error::chip_error::Chip8Error,
+        platform::variant::Platform,
+        quirks::quirk_profile::Quirks,
+        random::random_source::{RandomSource, SeededRandom},
+        state::save_state::{StateReader, StateWriter},
+    };
     use std::fs;
 
-    const CHIP8_FONTSET: [u8; 80] = [
+    /// Width of the display in pixels
+    pub const WIDTH: usize = 64;
+    /// Height of the display in pixels
+    pub const HEIGHT: usize = 32;
+    /// Width of the SUPER-CHIP high resolution display in pixels
+    pub const HIRES_WIDTH: usize = 128;
+    /// Height of the SUPER-CHIP high resolution display in pixels
+    pub const HIRES_HEIGHT: usize = 64;
+    /// Size of the addressable memory in bytes, the full 64 KiB of XO-CHIP
+    pub const MEMORY_SIZE: usize = 0x10000;
+    /// Number of XO-CHIP bit planes, each pixel of the framebuffer holds one bit per plane
+    pub const PLANES: usize = 2;
+    /// Address programs are loaded at and start executing from, unless the platform moves it
+    pub const PROGRAM_START: usize = 0x200;
+    /// CHIP-8X colour zones are 8 pixels wide and 1 pixel high
+    pub const ZONE_WIDTH: usize = 8;
+
+    /// Built-in 4x5 hex digit sprites, 5 bytes per digit from 0 to F
+    pub const CHIP8_FONTSET: [u8; 80] = [
         0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
         0x20, 0x60, 0x20, 0x20, 0x70, // 1
         0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
@@ -22,32 +45,32 @@ pub mod chip {
         0xF0, 0x80, 0xF0, 0x80, 0x80, // F
     ];
 
-    // Preserve this order
-    const KEY_ORDER: [Keycode; 16] = [
-        Keycode::X,
-        Keycode::Num1,
-        Keycode::Num2,
-        Keycode::Num3,
-        Keycode::Q,
-        Keycode::W,
-        Keycode::E,
-        Keycode::A,
-        Keycode::S,
-        Keycode::D,
-        Keycode::Z,
-        Keycode::C,
-        Keycode::Num4,
-        Keycode::R,
-        Keycode::F,
-        Keycode::V,
+    // SUPER-CHIP 8x10 font, stored straight after the small font
+    const BIG_FONTSET_START: usize = 0x50;
+    const BIG_FONTSET: [u8; 160] = [
+        0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+        0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+        0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+        0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+        0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+        0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+        0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+        0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+        0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+        0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+        0x3C, 0x7E, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, // A
+        0xFC, 0xFE, 0xC3, 0xC3, 0xFE, 0xFE, 0xC3, 0xC3, 0xFE, 0xFC, // B
+        0x3C, 0x7E, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0x7E, 0x3C, // C
+        0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+        0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xFF, 0xFF, // E
+        0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xC0, 0xC0, // F
     ];
 
     macro_rules! check_expr {
         ($self:expr, $bool_expr:expr) => {
+            $self.pc = $self.pc.wrapping_add(2);
             if $bool_expr {
-                $self.pc += 4;
-            } else {
-                $self.pc += 2;
+                $self.skip_next_instruction();
             }
         };
     }
@@ -63,64 +86,166 @@ pub mod chip {
 
     macro_rules! skip_instruction_key_press {
         ($self:expr, $op:tt, $sh8:expr) => {
-            check_expr!($self, $self.key[($self.g_reg[$sh8 as usize]) as usize] $op 0);
+            check_expr!($self, $self.key[($self.g_reg[$sh8 as usize] & 0xF) as usize] $op 0);
         };
     }
 
     macro_rules! update_register {
         ($self:expr, $op:tt, $left:expr) => {
             $self.g_reg[$left as usize] $op ($self.opcode & 0x00FF) as u8;
-            $self.pc += 2;
+            $self.writes.touch_register($left as usize);
+            $self.pc = $self.pc.wrapping_add(2);
         };
         ($self:expr, $op:tt, $left:expr, $right:expr) => {
             $self.g_reg[$left as usize] $op $self.g_reg[$right as usize];
-            $self.pc += 2;
+            $self.writes.touch_register($left as usize);
+            $self.pc = $self.pc.wrapping_add(2);
         };
     }
 
+    // Colour the CHIP-8X interpreter fills the colour zones with, red
+    const DEFAULT_ZONE_COLOUR: u8 = 1;
+
+    // The registers VX to VY in order, counting down when X is greater than Y
+    fn register_range(x: u16, y: u16) -> Box<dyn Iterator<Item = usize>> {
+        let (x, y) = (x as usize, y as usize);
+        if x <= y {
+            Box::new(x..=y)
+        } else {
+            Box::new((y..=x).rev())
+        }
+    }
+
+    /// Memory addresses, registers and I written by the last instruction, including writes of
+    /// the value already held
+    #[derive(Clone, Debug, Default, PartialEq, Eq)]
+    pub struct Writes {
+        memory: Vec<u16>,
+        registers: u16,
+        index: bool,
+    }
+
+    impl Writes {
+        pub fn memory(&self, address: u16) -> bool {
+            self.memory.contains(&address)
+        }
+
+        pub const fn register(&self, register: u8) -> bool {
+            register < 16 && self.registers & (1 << register) != 0
+        }
+
+        pub const fn index(&self) -> bool {
+            self.index
+        }
+
+        fn touch_memory(&mut self, address: u16) {
+            if !self.memory(address) {
+                self.memory.push(address);
+            }
+        }
+
+        fn touch_register(&mut self, register: usize) {
+            self.registers |= 1 << register;
+        }
+
+        fn touch_index(&mut self) {
+            self.index = true;
+        }
+
+        fn clear(&mut self) {
+            self.memory.clear();
+            self.registers = 0;
+            self.index = false;
+        }
+    }
+
     pub struct Chip8 {
         opcode: u16,
-        memory: [u8; 4096],
+        memory: [u8; MEMORY_SIZE],
         g_reg: [u8; 16],
         ir: u16,
         pc: u16,
-        gfx: [u8; 64 * 32],
+        gfx: [u8; HIRES_WIDTH * HIRES_HEIGHT],
+        width: usize,
+        height: usize,
         delay_timer: u8,
         sound_timer: u8,
         stack: [u16; 16],
         sp: u16,
         key: [u8; 16],
         draw_flag: bool,
+        quirks: Quirks,
+        waiting_vblank: bool,
+        rpl_flags: [u8; 16],
+        exited: bool,
+        plane_mask: u8,
+        audio_pattern: Option<[u8; 16]>,
+        pitch: u8,
+        platform: Platform,
+        background_colour: u8,
+        zone_colours: [u8; (WIDTH / ZONE_WIDTH) * HEIGHT],
+        io_output: u8,
+        io_input: u8,
+        random: Box<dyn RandomSource>,
+        key_log: Option<Vec<(u8, bool)>>,
+        writes: Writes,
     }
 
     impl Default for Chip8 {
         fn default() -> Self {
-            Self::new()
+            Self::new(Quirks::default())
         }
     }
 
     impl Chip8 {
-        pub fn new() -> Chip8 {
-            let mut memory: [u8; 4096] = [0; 4096];
+        pub fn new(quirks: Quirks) -> Chip8 {
+            Self::with_platform(quirks, Platform::default())
+        }
+
+        pub fn with_platform(quirks: Quirks, platform: Platform) -> Chip8 {
+            let mut memory = [0; MEMORY_SIZE];
 
             memory[..80].clone_from_slice(&CHIP8_FONTSET[..80]);
+            memory[BIG_FONTSET_START..BIG_FONTSET_START + BIG_FONTSET.len()]
+                .clone_from_slice(&BIG_FONTSET);
 
             Chip8 {
                 opcode: 0,
                 memory,
                 g_reg: [0; 16],
                 ir: 0,
-                pc: 0x200,
-                gfx: [0; 64 * 32],
+                pc: platform.program_start() as u16,
+                gfx: [0; HIRES_WIDTH * HIRES_HEIGHT],
+                width: platform.resolution().0,
+                height: platform.resolution().1,
                 delay_timer: 0,
                 sound_timer: 0,
                 stack: [0; 16],
                 sp: 0,
                 key: [0; 16],
                 draw_flag: true,
+                quirks,
+                waiting_vblank: false,
+                rpl_flags: [0; 16],
+                exited: false,
+                plane_mask: 0x1,
+                audio_pattern: None,
+                pitch: 64,
+                platform,
+                background_colour: 0,
+                zone_colours: [DEFAULT_ZONE_COLOUR; (WIDTH / ZONE_WIDTH) * HEIGHT],
+                io_output: 0,
+                io_input: 0,
+                random: Box::new(SeededRandom::default()),
+                key_log: None,
+                writes: Writes::default(),
             }
         }
 
+        pub const fn quirks(&self) -> Quirks {
+            self.quirks
+        }
+
         pub const fn should_play_sound(&self) -> bool {
             self.sound_timer != 0
         }
@@ -133,32 +258,337 @@ pub mod chip {
             self.draw_flag = false
         }
 
-        pub fn load(&mut self, path: &str) -> Result<(), String> {
-            let data = fs::read(path);
+        pub fn load(&mut self, path: &str) -> Result<(), Chip8Error> {
+            let data = fs::read(path)?;
 
-            let data = match data {
-                Ok(_) => data.unwrap(),
-                Err(_) => return Err("No such file or directory".to_string()),
-            };
+            self.load_rom(&data)
+        }
 
-            if (4096 - 512) > data.len() {
-                self.memory[512..512 + data.len()].clone_from_slice(&data[..data.len()]);
+        pub fn load_rom(&mut self, data: &[u8]) -> Result<(), Chip8Error> {
+            let start = self.platform.program_start();
+            if data.len() <= MEMORY_SIZE - start {
+                self.memory[start..start + data.len()].clone_from_slice(data);
                 Ok(())
             } else {
-                // too large
-                Err("Could Not Load Rom, It's too large".to_string())
+                Err(Chip8Error::RomTooLarge {
+                    size: data.len(),
+                    max: MEMORY_SIZE - start,
+                })
+            }
+        }
+
+        /// Snapshots the whole machine into a versioned binary save state
+        pub fn save_state(&self) -> Vec<u8> {
+            let mut state = StateWriter::new();
+
+            state.u8(self.platform.id());
+            state.u8(self.quirks.to_bits());
+            state.u16(self.opcode);
+            state.bytes(&self.memory);
+            state.bytes(&self.g_reg);
+            state.u16(self.ir);
+            state.u16(self.pc);
+            self.stack.iter().for_each(|&address| state.u16(address));
+            state.u16(self.sp);
+            state.u8(self.delay_timer);
+            state.u8(self.sound_timer);
+            state.bytes(&self.key);
+            state.u16(self.width as u16);
+            state.u16(self.height as u16);
+            state.bytes(&self.gfx);
+            state.u8(self.draw_flag as u8);
+            state.u8(self.waiting_vblank as u8);
+            state.u8(self.exited as u8);
+            state.bytes(&self.rpl_flags);
+            state.u8(self.plane_mask);
+            state.u8(self.audio_pattern.is_some() as u8);
+            state.bytes(&self.audio_pattern.unwrap_or_default());
+            state.u8(self.pitch);
+            state.u8(self.background_colour);
+            state.bytes(&self.zone_colours);
+            state.u8(self.io_output);
+            state.u8(self.io_input);
+
+            state.finish()
+        }
+
+        /// Restores a save state made by `save_state`, leaving the machine untouched when the
+        /// state is invalid or from an incompatible version
+        pub fn load_state(&mut self, data: &[u8]) -> Result<(), Chip8Error> {
+            let mut state = StateReader::new(data)?;
+            let platform = Platform::from_id(state.u8()?).ok_or(Chip8Error::InvalidState)?;
+            let mut chip8 = Chip8::with_platform(Quirks::from_bits(state.u8()?), platform);
+
+            chip8.opcode = state.u16()?;
+            chip8.memory = state.array()?;
+            chip8.g_reg = state.array()?;
+            chip8.ir = state.u16()?;
+            chip8.pc = state.u16()?;
+            for address in chip8.stack.iter_mut() {
+                *address = state.u16()?;
+            }
+            chip8.sp = state.u16()?;
+            chip8.delay_timer = state.u8()?;
+            chip8.sound_timer = state.u8()?;
+            chip8.key = state.array()?;
+            chip8.width = state.u16()? as usize;
+            chip8.height = state.u16()? as usize;
+            chip8.gfx = state.array()?;
+            chip8.draw_flag = state.bool()?;
+            chip8.waiting_vblank = state.bool()?;
+            chip8.exited = state.bool()?;
+            chip8.rpl_flags = state.array()?;
+            chip8.plane_mask = state.u8()?;
+            let has_pattern = state.bool()?;
+            let pattern = state.array()?;
+            chip8.audio_pattern = has_pattern.then_some(pattern);
+            chip8.pitch = state.u8()?;
+            chip8.background_colour = state.u8()?;
+            chip8.zone_colours = state.array()?;
+            chip8.io_output = state.u8()?;
+            chip8.io_input = state.u8()?;
+
+            // Addresses the machine will fetch from must hold a whole opcode
+            let fetchable = |address: u16| (address as usize) < MEMORY_SIZE - 1;
+            if chip8.sp as usize > chip8.stack.len()
+                || !fetchable(chip8.pc)
+                || !chip8.stack.iter().all(|&address| fetchable(address))
+                || chip8.width * chip8.height > chip8.gfx.len()
+                || chip8.width == 0
+                || chip8.height == 0
+            {
+                return Err(Chip8Error::InvalidState);
             }
+
+            // The random number generator and input log carry on from the current machine
+            std::mem::swap(&mut chip8.random, &mut self.random);
+            chip8.key_log = self.key_log.take();
+
+            *self = chip8;
+            self.draw_flag = true;
+            Ok(())
         }
 
-        fn set_register_on_borrow(&mut self, left: u16, right: u16) {
-            if self.g_reg[left as usize] > self.g_reg[right as usize] {
-                self.g_reg[0xF] = 0;
+        // The address `offset` bytes past I, failing when it lies outside of memory
+        fn ir_offset(&self, offset: usize) -> Result<usize, Chip8Error> {
+            let address = self.ir as usize + offset;
+            if address < MEMORY_SIZE {
+                Ok(address)
             } else {
-                self.g_reg[0xF] = 1;
+                Err(Chip8Error::IrOutOfRange {
+                    ir: self.ir,
+                    address: self.pc,
+                })
+            }
+        }
+
+        const fn unknown_opcode(&self) -> Chip8Error {
+            Chip8Error::UnknownOpcode {
+                opcode: self.opcode,
+                address: self.pc,
             }
         }
 
-        pub fn cycle(&mut self) {
+        // Draws a sprite of the given size from memory at I into every selected plane, each row
+        // read as width / 8 bytes and the data for each plane following the previous one.
+        // VF is set to 1 if any screen pixels are flipped from set to unset, and to 0 otherwise
+        fn draw_sprite(
+            &mut self,
+            x: usize,
+            y: usize,
+            width: usize,
+            height: usize,
+        ) -> Result<(), Chip8Error> {
+            // The starting coordinate always wraps, the rest of the sprite is clipped or
+            // wrapped depending on the clipping quirk
+            let x = x % self.width;
+            let y = y % self.height;
+            let row_bytes = width / 8;
+            let planes = self.plane_mask.count_ones() as usize;
+            if planes > 0 && height > 0 {
+                self.ir_offset(planes * height * row_bytes - 1)?;
+            }
+            let mut address = self.ir as usize;
+
+            self.store_register(0xF, 0);
+
+            for plane in (0..PLANES).map(|plane| 1u8 << plane) {
+                if self.plane_mask & plane == 0 {
+                    continue;
+                }
+
+                for yline in 0..height {
+                    let row = y + yline;
+                    if self.quirks.clipping && row >= self.height {
+                        break;
+                    }
+
+                    let start = address + yline * row_bytes;
+                    let pixels = self.memory[start..start + row_bytes]
+                        .iter()
+                        .fold(0u16, |acc, &byte| acc << 8 | byte as u16);
+
+                    for xline in 0..width {
+                        let column = x + xline;
+                        if self.quirks.clipping && column >= self.width {
+                            break;
+                        }
+                        if (pixels >> (width - 1 - xline)) & 0x1 != 0 {
+                            let index = (column % self.width) + (row % self.height) * self.width;
+                            if self.gfx[index] & plane != 0 {
+                                self.store_register(0xF, 1);
+                            }
+                            self.gfx[index] ^= plane;
+                        }
+                    }
+                }
+
+                address += height * row_bytes;
+            }
+
+            Ok(())
+        }
+
+        // Clears the selected planes
+        fn clear_planes(&mut self) {
+            let mask = !self.plane_mask;
+            self.gfx.iter_mut().for_each(|pixel| *pixel &= mask);
+            self.draw_flag = true;
+        }
+
+        // XO-CHIP's F000 NNNN is twice as long as every other instruction
+        fn skip_next_instruction(&mut self) {
+            let next = (self.memory[self.pc as usize] as u16) << 8
+                | self.memory[self.pc as usize + 1] as u16;
+            self.pc = self.pc.wrapping_add(if next == 0xF000 { 4 } else { 2 });
+        }
+
+        fn set_resolution(&mut self, width: usize, height: usize) {
+            self.width = width;
+            self.height = height;
+            self.gfx.fill(0x0);
+            self.draw_flag = true;
+        }
+
+        // Scrolls the selected planes down for a positive amount of rows and up for a negative one
+        fn scroll_vertical(&mut self, amount: isize) {
+            let shift = amount.unsigned_abs().min(self.height) * self.width;
+            let mask = self.plane_mask;
+            let display = &mut self.gfx[..self.width * self.height];
+            let previous = display.to_vec();
+            for (i, pixel) in display.iter_mut().enumerate() {
+                let source = if amount > 0 {
+                    i.checked_sub(shift)
+                } else {
+                    Some(i + shift).filter(|&source| source < previous.len())
+                };
+                let moved = source.map_or(0, |source| previous[source]);
+                *pixel = (*pixel & !mask) | (moved & mask);
+            }
+            self.draw_flag = true;
+        }
+
+        // Scrolls the selected planes right for a positive amount and left for a negative one
+        fn scroll_horizontal(&mut self, amount: isize) {
+            let shift = amount.unsigned_abs().min(self.width);
+            let (width, mask) = (self.width, self.plane_mask);
+            for row in self.gfx[..self.width * self.height].chunks_mut(width) {
+                let previous = row.to_vec();
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let source = if amount > 0 {
+                        x.checked_sub(shift)
+                    } else {
+                        Some(x + shift).filter(|&source| source < width)
+                    };
+                    let moved = source.map_or(0, |source| previous[source]);
+                    *pixel = (*pixel & !mask) | (moved & mask);
+                }
+            }
+            self.draw_flag = true;
+        }
+
+        // CHIP-8X BXYN colouring. With N = 0 the low nibbles of VX and VX+1 hold the first 8x4
+        // zone column and row and the high nibbles how many more to colour, otherwise VX and
+        // VX+1 are a pixel position and N rows of 8x1 zones are coloured from there
+        fn colour_zones(&mut self, x: usize, y: usize, rows: usize) {
+            let colour = self.g_reg[y] & 0x7;
+            let horizontal = self.g_reg[x] as usize;
+            let vertical = self.g_reg[(x + 1) & 0xF] as usize;
+            let columns = WIDTH / ZONE_WIDTH;
+
+            let (column_range, row_range) = if rows == 0 {
+                let first_row = (vertical & 0xF) * 4;
+                (
+                    (horizontal & 0xF)..=(horizontal & 0xF) + (horizontal >> 4),
+                    first_row..first_row + ((vertical >> 4) + 1) * 4,
+                )
+            } else {
+                let column = (horizontal % WIDTH) / ZONE_WIDTH;
+                (column..=column, vertical..vertical + rows)
+            };
+
+            for row in row_range {
+                for column in column_range.clone() {
+                    self.zone_colours[(row % HEIGHT) * columns + column % columns] = colour;
+                }
+            }
+            self.draw_flag = true;
+        }
+
+        fn reset_vf(&mut self) {
+            if self.quirks.vf_reset {
+                self.store_register(0xF, 0);
+            }
+        }
+
+        // The value shifted by 8XY6 and 8XYE, VX with the shift quirk and VY without
+        const fn shift_source(&self, shr8: u16, shr4: u16) -> u8 {
+            if self.quirks.shift {
+                self.g_reg[shr8 as usize]
+            } else {
+                self.g_reg[shr4 as usize]
+            }
+        }
+
+        // Stores the left register minus the right one in the target, VF is set to 0 when
+        // there's a borrow and 1 when there isn't
+        fn subtract(&mut self, target: u16, left: u16, right: u16) {
+            let (difference, borrow) =
+                self.g_reg[left as usize].overflowing_sub(self.g_reg[right as usize]);
+            self.store_register(target as usize, difference);
+            self.store_register(0xF, !borrow as u8);
+            self.pc = self.pc.wrapping_add(2);
+        }
+
+        // Register, memory and I writes made by instructions, recorded for watchpoints
+        fn store_register(&mut self, register: usize, value: u8) {
+            self.g_reg[register] = value;
+            self.writes.touch_register(register);
+        }
+
+        fn store_memory(&mut self, address: usize, value: u8) {
+            self.memory[address] = value;
+            self.writes.touch_memory(address as u16);
+        }
+
+        fn store_ir(&mut self, ir: u16) {
+            self.ir = ir;
+            self.writes.touch_index();
+        }
+
+        pub fn cycle(&mut self) -> Result<(), Chip8Error> {
+            self.writes.clear();
+
+            // With the display wait quirk nothing runs between a draw and the next vertical blank
+            if self.waiting_vblank || self.exited {
+                return Ok(());
+            }
+
+            // The opcode must lie in memory, F000 NNNN checks its second word itself
+            if self.pc as usize + 1 >= MEMORY_SIZE {
+                return Err(Chip8Error::PcOutOfRange { pc: self.pc });
+            }
+
             // Fetch self.opcode
             self.opcode = (self.memory[self.pc as usize] as u16) << 8
                 | (self.memory[(self.pc + 1) as usize] as u16);
@@ -169,32 +599,86 @@ pub mod chip {
             // Process self.opcode
             match self.opcode & 0xF000 {
                 0x0000 => {
-                    match self.opcode & 0x000F {
-                        0x0000 => {
-                            // 0x00E0=>{ Clears the scree=>{
-                            for i in 0..2048 {
-                                self.gfx[i] = 0x0;
-                            }
-                            self.draw_flag = true;
-                            self.pc += 2;
+                    match self.opcode & 0x0FFF {
+                        0x00E0 => {
+                            // 0x00E0=>{ Clears the selected planes of the scree=>{
+                            self.clear_planes();
+                            self.pc = self.pc.wrapping_add(2);
                         }
-                        0x000E => {
+                        0x00EE => {
                             // 0x00EE=>{ Returns from subroutin=>{
+                            if self.sp == 0 {
+                                return Err(Chip8Error::StackUnderflow { address: self.pc });
+                            }
                             self.sp -= 1; // 16 levels of stack, decrease stack pointer to prevent overwrite
                             self.pc = self.stack[self.sp as usize]; // Put the stored return address from the stack back into the program counter
-                            self.pc += 2; // Don't forget to increase the program counter!
+                            self.pc = self.pc.wrapping_add(2); // Don't forget to increase the program counter!
+                        }
+                        0x00C0..=0x00CF => {
+                            // 0x00CN=>{ Scrolls the display down by N pixels=>{
+                            self.scroll_vertical((self.opcode & 0x000F) as isize);
+                            self.pc = self.pc.wrapping_add(2);
+                        }
+                        0x00D0..=0x00DF => {
+                            // 0x00DN=>{ Scrolls the display up by N pixels=>{
+                            self.scroll_vertical(-((self.opcode & 0x000F) as isize));
+                            self.pc = self.pc.wrapping_add(2);
+                        }
+                        0x00FB => {
+                            // 0x00FB=>{ Scrolls the display right by 4 pixels=>{
+                            self.scroll_horizontal(4);
+                            self.pc = self.pc.wrapping_add(2);
+                        }
+                        0x00FC => {
+                            // 0x00FC=>{ Scrolls the display left by 4 pixels=>{
+                            self.scroll_horizontal(-4);
+                            self.pc = self.pc.wrapping_add(2);
                         }
-                        _ => {
-                            println!("Unknown opcode [0x0000]=> {}", self.opcode);
+                        0x00FD => {
+                            // 0x00FD=>{ Exits the interpreter=>{
+                            self.exited = true;
                         }
+                        0x00FE => {
+                            // 0x00FE=>{ Switches to the 64x32 low resolution mode=>{
+                            self.set_resolution(WIDTH, HEIGHT);
+                            self.pc = self.pc.wrapping_add(2);
+                        }
+                        0x00FF => {
+                            // 0x00FF=>{ Switches to the 128x64 high resolution mode=>{
+                            self.set_resolution(HIRES_WIDTH, HIRES_HEIGHT);
+                            self.pc = self.pc.wrapping_add(2);
+                        }
+                        0x0230 if self.platform == Platform::HiresChip8 => {
+                            // 0x0230=>{ Clears the HIRES CHIP-8 scree=>{
+                            self.clear_planes();
+                            self.pc = self.pc.wrapping_add(2);
+                        }
+                        0x02A0 if self.platform == Platform::Chip8X => {
+                            // 0x02A0=>{ Steps the CHIP-8X background through blue, black, green and re=>{
+                            self.background_colour = (self.background_colour + 1) % 4;
+                            self.draw_flag = true;
+                            self.pc = self.pc.wrapping_add(2);
+                        }
+                        _ => return Err(self.unknown_opcode()),
                     }
                 }
                 0x1000 => {
                     // 0x1NNN=>{ Jumps to address NN=>{
-                    self.pc = self.opcode & 0x0FFF;
+                    // HIRES CHIP-8 programs start with a 1260 trampoline into the interpreter at 0x2C0
+                    if self.platform == Platform::HiresChip8
+                        && self.opcode == 0x1260
+                        && self.pc as usize == self.platform.program_start()
+                    {
+                        self.pc = 0x2C0;
+                    } else {
+                        self.pc = self.opcode & 0x0FFF;
+                    }
                 }
                 0x2000 => {
                     // 0x2NNN=>{ Calls subroutine at NNN=>{
+                    if self.sp as usize == self.stack.len() {
+                        return Err(Chip8Error::StackOverflow { address: self.pc });
+                    }
                     self.stack[self.sp as usize] = self.pc; // Store current address in stack
                     self.sp += 1; // Increment stack pointer
                     self.pc = self.opcode & 0x0FFF; // Set the program counter to the address at NNN
@@ -208,8 +692,28 @@ pub mod chip {
                     skip_instruction!(self, !=, shr8);
                 }
                 0x5000 => {
-                    // 0x5XY0=>{ Skips the next instruction if VX equals VY=>{
-                    skip_instruction!(self, ==, shr8, shr4);
+                    match self.opcode & 0x000F {
+                        0x0000 => {
+                            // 0x5XY0=>{ Skips the next instruction if VX equals VY=>{
+                            skip_instruction!(self, ==, shr8, shr4);
+                        }
+                        0x0002 => {
+                            // 0x5XY2=>{ Stores VX to VY in memory starting at address I, I is unchanged=>{
+                            for (offset, register) in register_range(shr8, shr4).enumerate() {
+                                let address = self.ir_offset(offset)?;
+                                self.store_memory(address, self.g_reg[register]);
+                            }
+                            self.pc = self.pc.wrapping_add(2);
+                        }
+                        0x0003 => {
+                            // 0x5XY3=>{ Fills VX to VY with values from memory starting at address I, I is unchanged=>{
+                            for (offset, register) in register_range(shr8, shr4).enumerate() {
+                                self.store_register(register, self.memory[self.ir_offset(offset)?]);
+                            }
+                            self.pc = self.pc.wrapping_add(2);
+                        }
+                        _ => return Err(self.unknown_opcode()),
+                    }
                 }
                 0x6000 => {
                     // 0x6XNN=>{ Sets VX to NN=>{
@@ -217,7 +721,11 @@ pub mod chip {
                 }
                 0x7000 => {
                     // 0x7XNN=>{ Adds NN to VX=>{
-                    update_register!(self, +=, shr8);
+                    self.store_register(
+                        shr8 as usize,
+                        self.g_reg[shr8 as usize].wrapping_add((self.opcode & 0x00FF) as u8),
+                    );
+                    self.pc = self.pc.wrapping_add(2);
                 }
                 0x8000 => {
                     match self.opcode & 0x000F {
@@ -228,51 +736,49 @@ pub mod chip {
                         0x0001 => {
                             // 0x8XY1=>{ Sets VX to "VX OR VY=>{
                             update_register!(self, |=, shr8, shr4);
+                            self.reset_vf();
                         }
                         0x0002 => {
                             // 0x8XY2=>{ Sets VX to "VX AND VY=>{
                             update_register!(self, &=, shr8, shr4);
+                            self.reset_vf();
                         }
                         0x0003 => {
                             // 0x8XY3=>{ Sets VX to "VX XOR VY=>{
                             update_register!(self, ^=, shr8, shr4);
+                            self.reset_vf();
                         }
                         0x0004 => {
                             // 0x8XY4=>{ Adds VY to VX. VF is set to 1 when there's a carry, and to 0 when there isn'=>{
-                            if self.g_reg[shr4 as usize] > (0xFF - self.g_reg[shr8 as usize]) {
-                                self.g_reg[0xF] = 1;
-                            } else {
-                                self.g_reg[0xF] = 0;
-                            }
-                            update_register!(self, +=, shr8, shr4);
+                            let (sum, carry) = self.g_reg[shr8 as usize]
+                                .overflowing_add(self.g_reg[shr4 as usize]);
+                            self.store_register(shr8 as usize, sum);
+                            self.store_register(0xF, carry as u8);
+                            self.pc = self.pc.wrapping_add(2);
                         }
                         0x0005 => {
                             // 0x8XY5=>{ VY is subtracted from VX. VF is set to 0 when there's a borrow, and 1 when there isn'=>{
-                            self.set_register_on_borrow(shr4, shr8);
-                            update_register!(self, -=, shr8, shr4);
+                            self.subtract(shr8, shr8, shr4);
                         }
                         0x0006 => {
                             // 0x8XY6=>{ Shifts VX right by one. VF is set to the value of the least significant bit of VX before the shif=>{
-                            self.g_reg[0xF] = self.g_reg[shr8 as usize] & 0x1;
-                            self.g_reg[shr8 as usize] >>= 1;
-                            self.pc += 2;
+                            let value = self.shift_source(shr8, shr4);
+                            self.store_register(shr8 as usize, value >> 1);
+                            self.store_register(0xF, value & 0x1);
+                            self.pc = self.pc.wrapping_add(2);
                         }
                         0x0007 => {
                             // 0x8XY7=>{ Sets VX to VY minus VX. VF is set to 0 when there's a borrow, and 1 when there isn'=>{
-                            self.set_register_on_borrow(shr8, shr4);
-                            self.g_reg[shr8 as usize] =
-                                self.g_reg[shr4 as usize] - self.g_reg[shr8 as usize];
-                            self.pc += 2;
+                            self.subtract(shr8, shr4, shr8);
                         }
                         0x000E => {
                             // 0x8XYE=>{ Shifts VX left by one. VF is set to the value of the most significant bit of VX before the shif=>{
-                            self.g_reg[0xF] = self.g_reg[shr8 as usize] >> 7;
-                            self.g_reg[shr8 as usize] <<= 1;
-                            self.pc += 2;
-                        }
-                        _ => {
-                            println!("Unknown opcode [0x8000]=> {}", self.opcode);
+                            let value = self.shift_source(shr8, shr4);
+                            self.store_register(shr8 as usize, value << 1);
+                            self.store_register(0xF, value >> 7);
+                            self.pc = self.pc.wrapping_add(2);
                         }
+                        _ => return Err(self.unknown_opcode()),
                     }
                 }
                 0x9000 => {
@@ -281,18 +787,30 @@ pub mod chip {
                 }
                 0xA000 => {
                     // ANNN=>{ Sets I to the address NN=>{
-                    self.ir = (self.opcode & 0x0FFF) as u16;
-                    self.pc += 2;
+                    self.store_ir(self.opcode & 0x0FFF);
+                    self.pc = self.pc.wrapping_add(2);
+                }
+                0xB000 if self.platform == Platform::Chip8X => {
+                    // BXY0=>{ Sets the colour of the 8x4 zones in VX and VX+1 to VY=>{
+                    // BXYN=>{ Sets the colour of N 8x1 zones from pixel (VX, VX+1) to VY=>{
+                    self.colour_zones(
+                        shr8 as usize,
+                        shr4 as usize,
+                        (self.opcode & 0x000F) as usize,
+                    );
+                    self.pc = self.pc.wrapping_add(2);
                 }
                 0xB000 => {
                     // BNNN=>{ Jumps to the address NNN plus self.g_reg=>{
-                    self.pc = (self.opcode & 0x0FFF) + self.g_reg[0] as u16;
+                    // With the jump quirk this is BXNN, jumping to XNN plus VX
+                    let offset = if self.quirks.jump { shr8 } else { 0 };
+                    self.pc = (self.opcode & 0x0FFF) + self.g_reg[offset as usize] as u16;
                 }
                 0xC000 => {
                     // CXNN=>{ Sets VX to a random number and N=>{
-                    self.g_reg[shr8 as usize] =
-                        (rand::thread_rng().gen::<u8>() % 0xFF) & (self.opcode & 0x00FF) as u8;
-                    self.pc += 2;
+                    let value = self.random.next_byte() & (self.opcode & 0x00FF) as u8;
+                    self.store_register(shr8 as usize, value);
+                    self.pc = self.pc.wrapping_add(2);
                 }
                 0xD000 => {
                     // DXYN=>{ Draws a sprite at coordinate (VX, VY) that has a width of 8 pixels and a height of N pixels=>{
@@ -301,29 +819,17 @@ pub mod chip {
                     // VF is set to 1 if any screen pixels are flipped from set to unset when the sprite is drawn,
                     // and to 0 if that doesn't happen
 
-                    let x = self.g_reg[shr8 as usize] as u16;
-                    let y = self.g_reg[shr4 as usize] as u16;
-                    let height = (self.opcode & 0x000F) as u16;
-
-                    self.g_reg[0xF] = 0;
-
-                    for yline in 0..height {
-                        let pixel = self.memory[(self.ir + yline) as usize] as u16;
-                        for xline in 0..8 {
-                            if (pixel & (0x80 >> xline)) != 0 {
-                                let index = (x + xline + ((y + yline) * 64)) as usize;
-                                if index < self.gfx.len() {
-                                    if self.gfx[index] == 1 {
-                                        self.g_reg[0xF] = 1;
-                                    }
-                                    self.gfx[index] ^= 1;
-                                }
-                            }
-                        }
+                    // DXY0 draws a 16x16 sprite instead
+                    let x = self.g_reg[shr8 as usize] as usize;
+                    let y = self.g_reg[shr4 as usize] as usize;
+                    match self.opcode & 0x000F {
+                        0 => self.draw_sprite(x, y, 16, 16)?,
+                        height => self.draw_sprite(x, y, 8, height as usize)?,
                     }
 
                     self.draw_flag = true;
-                    self.pc += 2;
+                    self.waiting_vblank = self.quirks.display_wait;
+                    self.pc = self.pc.wrapping_add(2);
                 }
                 0xE000 => {
                     match self.opcode & 0x00FF {
@@ -335,17 +841,41 @@ pub mod chip {
                             // EXA1=>{ Skips the next instruction if the key stored in VX isn't presse=>{
                             skip_instruction_key_press!(self, ==, shr8);
                         }
-                        _ => {
-                            println!("Unknown opcode [0xE000]=> {}", self.opcode);
-                        }
+                        _ => return Err(self.unknown_opcode()),
                     }
                 }
                 0xF000 => {
                     match self.opcode & 0x00FF {
+                        0x0000 if shr8 == 0 => {
+                            // F000 NNNN=>{ Sets I to the 16 bit address NNNN stored in the next word=>{
+                            if self.pc as usize + 3 >= MEMORY_SIZE {
+                                return Err(Chip8Error::PcOutOfRange { pc: self.pc });
+                            }
+                            self.store_ir(
+                                (self.memory[self.pc as usize + 2] as u16) << 8
+                                    | self.memory[self.pc as usize + 3] as u16,
+                            );
+                            // At the very end of memory this wraps around to 0 like a skip does
+                            self.pc = self.pc.wrapping_add(4);
+                        }
+                        0x0001 => {
+                            // FN01=>{ Selects the bit planes N drawn to, cleared and scrolled=>{
+                            self.plane_mask = shr8 as u8 & 0x3;
+                            self.pc = self.pc.wrapping_add(2);
+                        }
+                        0x0002 if shr8 == 0 => {
+                            // F002=>{ Loads the 16 byte audio pattern buffer from memory starting at address I=>{
+                            let mut pattern = [0; 16];
+                            for (i, byte) in pattern.iter_mut().enumerate() {
+                                *byte = self.memory[self.ir_offset(i)?];
+                            }
+                            self.audio_pattern = Some(pattern);
+                            self.pc = self.pc.wrapping_add(2);
+                        }
                         0x0007 => {
                             // FX07=>{ Sets VX to the value of the delay time=>{
-                            self.g_reg[shr8 as usize] = self.delay_timer;
-                            self.pc += 2;
+                            self.store_register(shr8 as usize, self.delay_timer);
+                            self.pc = self.pc.wrapping_add(2);
                         }
                         0x000A => {
                             // FX0A=>{ A key press is awaited, and then stored in self.g_reg=>{
@@ -354,123 +884,520 @@ pub mod chip {
 
                             for i in 0..16 {
                                 if self.key[i] != 0 {
-                                    self.g_reg[shr8 as usize] = i as u8;
+                                    self.store_register(shr8 as usize, i as u8);
                                     key_press = true;
                                 }
                             }
 
                             // If we didn't received a keypress, skip this cycle and try again.
                             if !key_press {
-                                return;
+                                return Ok(());
                             }
 
-                            self.pc += 2;
+                            self.pc = self.pc.wrapping_add(2);
                         }
 
                         0x0015 => {
                             // FX15=>{ Sets the delay timer to self.g_reg=>{
                             self.delay_timer = self.g_reg[shr8 as usize];
-                            self.pc += 2;
+                            self.pc = self.pc.wrapping_add(2);
                         }
 
                         0x0018 => {
                             // FX18=>{ Sets the sound timer to self.g_reg=>{
                             self.sound_timer = self.g_reg[shr8 as usize];
-                            self.pc += 2;
+                            self.pc = self.pc.wrapping_add(2);
                         }
                         0x001E => {
                             // FX1E=>{ Adds VX to =>{
-                            if self.ir + self.g_reg[shr8 as usize] as u16 > 0xFFF {
-                                // VF is set to 1 when range overflow (I+VX>0xFFF), and 0 when there isn't.
-                                self.g_reg[0xF] = 1;
-                            } else {
-                                self.g_reg[0xF] = 0;
-                            }
-                            self.ir += self.g_reg[shr8 as usize] as u16;
-                            self.pc += 2;
+                            let ir = self.ir as usize + self.g_reg[shr8 as usize] as usize;
+                            // VF is set to 1 when range overflow (I+VX>0xFFF), and 0 when there isn't.
+                            self.store_register(0xF, (ir > 0xFFF) as u8);
+                            self.store_ir(ir as u16);
+                            self.pc = self.pc.wrapping_add(2);
                         }
                         0x0029 => {
                             // FX29=>{ Sets I to the location of the sprite for the character in VX. Characters 0-F (in hexadecimal) are represented by a 4x5 fon=>{
-                            self.ir = (self.g_reg[shr8 as usize] * 0x5) as u16;
-                            self.pc += 2;
+                            self.store_ir((self.g_reg[shr8 as usize] & 0xF) as u16 * 0x5);
+                            self.pc = self.pc.wrapping_add(2);
+                        }
+                        0x0030 => {
+                            // FX30=>{ Sets I to the location of the 8x10 sprite for the character in VX=>{
+                            self.store_ir(
+                                (BIG_FONTSET_START
+                                    + (self.g_reg[shr8 as usize] & 0xF) as usize * 10)
+                                    as u16,
+                            );
+                            self.pc = self.pc.wrapping_add(2);
                         }
                         0x0033 => {
                             // FX33=>{ Stores the Binary-coded decimal representation of VX at the addresses I, I plus 1, and I plus =>{
-                            self.memory[self.ir as usize] = self.g_reg[shr8 as usize] / 100;
-                            self.memory[(self.ir + 1) as usize] =
-                                (self.g_reg[shr8 as usize] / 10) % 10;
-                            self.memory[(self.ir + 2) as usize] =
-                                (self.g_reg[shr8 as usize] % 100) % 10;
-                            self.pc += 2;
+                            let address = self.ir_offset(2)? - 2;
+                            self.store_memory(address, self.g_reg[shr8 as usize] / 100);
+                            self.store_memory(address + 1, (self.g_reg[shr8 as usize] / 10) % 10);
+                            self.store_memory(address + 2, (self.g_reg[shr8 as usize] % 100) % 10);
+                            self.pc = self.pc.wrapping_add(2);
+                        }
+                        0x003A => {
+                            // FX3A=>{ Sets the audio pattern playback pitch to VX=>{
+                            self.pitch = self.g_reg[shr8 as usize];
+                            self.pc = self.pc.wrapping_add(2);
                         }
                         0x0055 => {
                             // FX55=>{ Stores V0 to VX in memory starting at address =>{
-                            for i in 0..=shr8 {
-                                self.memory[(self.ir + i) as usize] = self.g_reg[i as usize];
+                            for i in 0..=shr8 as usize {
+                                let address = self.ir_offset(i)?;
+                                self.store_memory(address, self.g_reg[i]);
                             }
 
                             // On the original interpreter, when the operation is done, self.ir = self.ir + X + 1.
-                            self.ir += shr8 + 1u16;
-                            self.pc += 2;
+                            if self.quirks.load_store {
+                                self.store_ir(self.ir.wrapping_add(shr8 + 1));
+                            }
+                            self.pc = self.pc.wrapping_add(2);
                         }
                         0x0065 => {
                             // FX65=>{ Fills V0 to VX with values from memory starting at address =>{
-                            for i in 0..=shr8 {
-                                self.g_reg[i as usize] = self.memory[(self.ir + i) as usize];
+                            for i in 0..=shr8 as usize {
+                                self.store_register(i, self.memory[self.ir_offset(i)?]);
                             }
 
                             // On the original interpreter, when the operation is done, self.ir = self.ir + X + 1.
-                            self.ir += shr8 + 1u16;
-                            self.pc += 2;
+                            if self.quirks.load_store {
+                                self.store_ir(self.ir.wrapping_add(shr8 + 1));
+                            }
+                            self.pc = self.pc.wrapping_add(2);
+                        }
+                        0x00F8 if self.platform == Platform::Chip8X => {
+                            // FXF8=>{ Outputs VX to the I/O port=>{
+                            self.io_output = self.g_reg[shr8 as usize];
+                            self.pc = self.pc.wrapping_add(2);
                         }
-                        _ => {
-                            println!("Unknown opcode [0xF000]=> {}", self.opcode);
+                        0x00FB if self.platform == Platform::Chip8X => {
+                            // FXFB=>{ Sets VX to the value on the I/O input port=>{
+                            self.store_register(shr8 as usize, self.io_input);
+                            self.pc = self.pc.wrapping_add(2);
+                        }
+                        0x0075 => {
+                            // FX75=>{ Stores V0 to VX in the RPL user flags=>{
+                            for i in 0..=shr8 as usize {
+                                self.rpl_flags[i] = self.g_reg[i];
+                            }
+                            self.pc = self.pc.wrapping_add(2);
                         }
+                        0x0085 => {
+                            // FX85=>{ Fills V0 to VX from the RPL user flags=>{
+                            for i in 0..=shr8 as usize {
+                                self.store_register(i, self.rpl_flags[i]);
+                            }
+                            self.pc = self.pc.wrapping_add(2);
+                        }
+                        _ => return Err(self.unknown_opcode()),
                     }
                 }
-                _ => {
-                    println!("Unknown opcode: {}", self.opcode);
-                }
+                _ => return Err(self.unknown_opcode()),
             }
 
-            // Update timers
-            if self.delay_timer > 0 {
-                self.delay_timer -= 1;
-            }
+            Ok(())
+        }
 
-            if self.sound_timer > 0 {
-                if self.sound_timer == 1 {
-                    println!("BEEP!");
-                }
-                self.sound_timer -= 1;
+        /// Signals the vertical blank, decrementing the delay and sound timers and ending any
+        /// display wait, must be called at 60 Hz of emulated time
+        pub fn tick_timers(&mut self) {
+            self.waiting_vblank = false;
+            self.delay_timer = self.delay_timer.saturating_sub(1);
+            self.sound_timer = self.sound_timer.saturating_sub(1);
+        }
+
+        /// The framebuffer at the active resolution, one byte per pixel in row-major order,
+        /// holding one bit per XO-CHIP plane so `0` is unlit and `1` to `3` select a colour
+        pub fn gfx(&self) -> &[u8] {
+            &self.gfx[..self.width * self.height]
+        }
+
+        /// Active display width in pixels
+        pub const fn width(&self) -> usize {
+            self.width
+        }
+
+        /// Active display height in pixels
+        pub const fn height(&self) -> usize {
+            self.height
+        }
+
+        /// The XO-CHIP 128 bit audio pattern loaded by `F002`, `None` until a program loads one
+        pub const fn audio_pattern(&self) -> Option<&[u8; 16]> {
+            self.audio_pattern.as_ref()
+        }
+
+        pub const fn pitch(&self) -> u8 {
+            self.pitch
+        }
+
+        /// Rate in bits per second the audio pattern is played back at for the current pitch
+        pub fn audio_pattern_rate(&self) -> f32 {
+            4000.0 * 2f32.powf((self.pitch as f32 - 64.0) / 48.0)
+        }
+
+        pub const fn platform(&self) -> Platform {
+            self.platform
+        }
+
+        /// The CHIP-8X background colour, 0 to 3 for blue, black, green and red
+        pub const fn background_colour(&self) -> u8 {
+            self.background_colour
+        }
+
+        /// The CHIP-8X foreground colour of the pixel at `(x, y)`, 0 to 7 for black, red, blue,
+        /// violet, green, yellow, aqua and white
+        pub const fn zone_colour(&self, x: usize, y: usize) -> u8 {
+            self.zone_colours[(y % HEIGHT) * (WIDTH / ZONE_WIDTH) + (x % WIDTH) / ZONE_WIDTH]
+        }
+
+        /// The last value written to the CHIP-8X I/O port by `FXF8`
+        pub const fn io_output(&self) -> u8 {
+            self.io_output
+        }
+
+        /// Sets the value read from the CHIP-8X I/O port by `FXFB`
+        pub fn set_io_input(&mut self, value: u8) {
+            self.io_input = value;
+        }
+
+        /// The XO-CHIP bit planes selected by `FN01`
+        pub const fn plane_mask(&self) -> u8 {
+            self.plane_mask
+        }
+
+        /// Whether the program has exited with `00FD`
+        pub const fn has_exited(&self) -> bool {
+            self.exited
+        }
+
+        /// Whether execution is held until the next vertical blank by the display wait quirk
+        pub const fn is_waiting_vblank(&self) -> bool {
+            self.waiting_vblank
+        }
+
+        pub const fn registers(&self) -> &[u8; 16] {
+            &self.g_reg
+        }
+
+        /// What the last instruction wrote, watchpoints stop on these
+        pub const fn writes(&self) -> &Writes {
+            &self.writes
+        }
+
+        pub const fn pc(&self) -> u16 {
+            self.pc
+        }
+
+        pub const fn ir(&self) -> u16 {
+            self.ir
+        }
+
+        pub const fn sp(&self) -> u16 {
+            self.sp
+        }
+
+        /// The return addresses currently on the stack, oldest first
+        pub fn stack(&self) -> &[u16] {
+            &self.stack[..self.sp as usize]
+        }
+
+        pub const fn memory(&self) -> &[u8] {
+            &self.memory
+        }
+
+        pub const fn delay_timer(&self) -> u8 {
+            self.delay_timer
+        }
+
+        pub const fn sound_timer(&self) -> u8 {
+            self.sound_timer
+        }
+
+        /// Sets the general purpose register V`register`
+        pub fn set_register(&mut self, register: usize, value: u8) {
+            if let Some(reg) = self.g_reg.get_mut(register) {
+                *reg = value;
             }
         }
 
-        pub fn update_quads(&self, canvas: &mut Canvas<Window>) {
-            canvas.set_draw_color(sdl2::pixels::Color::BLACK);
+        pub fn set_ir(&mut self, ir: u16) {
+            self.ir = ir;
+        }
+
+        pub fn set_pc(&mut self, pc: u16) {
+            self.pc = pc;
+        }
 
-            let size = canvas.window().size();
-            let _background = canvas.fill_rect(Rect::new(0, 0, size.0, size.1));
+        /// Sets the stack pointer, at most the 16 levels of the stack
+        pub fn set_sp(&mut self, sp: u16) {
+            self.sp = sp.min(self.stack.len() as u16);
+        }
+
+        pub fn set_delay_timer(&mut self, value: u8) {
+            self.delay_timer = value;
+        }
 
-            for y in 0..32 {
-                let y_offset = y * 64;
-                for x in 0..64 {
-                    if self.gfx[y_offset + x] == 1 {
-                        canvas.set_draw_color(sdl2::pixels::Color::WHITE);
+        pub fn set_sound_timer(&mut self, value: u8) {
+            self.sound_timer = value;
+        }
+
+        /// Writes `data` to memory from `address`, dropping what lies past the end of memory
+        pub fn write_memory(&mut self, address: usize, data: &[u8]) {
+            for (byte, &value) in self.memory.iter_mut().skip(address).zip(data) {
+                *byte = value;
+            }
+            self.draw_flag = true;
+        }
 
-                        let _pixel =
-                            canvas.fill_rect(Rect::new((x * 20) as i32, (y * 20) as i32, 20, 20));
+        /// Sets the state of the hex keypad key `key` (0x0 - 0xF)
+        pub fn set_action(&mut self, key: u8, pressed: bool) {
+            if let Some(state) = self.key.get_mut(key as usize) {
+                if *state != pressed as u8 {
+                    if let Some(key_log) = &mut self.key_log {
+                        key_log.push((key, pressed));
                     }
                 }
+                *state = pressed as u8;
             }
         }
 
-        pub fn set_action(&mut self, code: Keycode, on_or_off: u8) {
-            for (i, keycode) in KEY_ORDER.into_iter().enumerate() {
-                if code == keycode {
-                    self.key[i] = on_or_off;
-                }
+        /// Whether the hex keypad key `key` is held down
+        pub fn is_key_pressed(&self, key: u8) -> bool {
+            self.key.get(key as usize).is_some_and(|&state| state != 0)
+        }
+
+        /// Starts or stops logging the keypad state changes made through `set_action`
+        pub fn log_input(&mut self, enabled: bool) {
+            self.key_log = enabled.then(Vec::new);
+        }
+
+        /// Takes the keypad state changes logged since the last call, as `(key, pressed)`
+        pub fn take_input_log(&mut self) -> Vec<(u8, bool)> {
+            self.key_log
+                .as_mut()
+                .map(std::mem::take)
+                .unwrap_or_default()
+        }
+
+        /// Seed of the random source of `CXNN`, when it can be reproduced from one
+        pub fn seed(&self) -> Option<u64> {
+            self.random.seed()
+        }
+
+        /// Restarts the random number generator from `seed`
+        pub fn reseed(&mut self, seed: u64) {
+            self.set_random_source(Box::new(SeededRandom::new(seed)));
+        }
+
+        /// Replaces the source of the random numbers of `CXNN`
+        pub fn set_random_source(&mut self, random: Box<dyn RandomSource>) {
+            self.random = random;
+        }
+
+        /// Powers the machine back on with its quirks, platform and random source, the random
+        /// sequence starting over
+        pub fn reset(&mut self) {
+            let mut chip8 = Chip8::with_platform(self.quirks, self.platform);
+            std::mem::swap(&mut chip8.random, &mut self.random);
+            chip8.random.reset();
+            *self = chip8;
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::chip8::random::random_source::ScriptedRandom;
+
+        // Runs the first `cycles` instructions of `program`
+        fn run(quirks: Quirks, program: &[u8], cycles: usize) -> Chip8 {
+            let mut chip8 = Chip8::new(quirks);
+            chip8.load_rom(program).unwrap();
+            for _ in 0..cycles {
+                chip8.cycle().unwrap();
             }
+            chip8
+        }
+
+        // Positions of the pixels set in any plane, row by row
+        fn lit(chip8: &Chip8) -> Vec<(usize, usize)> {
+            let width = chip8.width();
+            chip8.gfx()[..width * chip8.height()]
+                .iter()
+                .enumerate()
+                .filter(|(_, &pixel)| pixel != 0)
+                .map(|(i, _)| (i % width, i / width))
+                .collect()
+        }
+
+        #[test]
+        fn scrolls_move_the_display() {
+            // LD V0, 8; LD V1, 4; LD I, sprite; DRW V0, V1, 1; SCD 2; SCR; SCL; SCL; SCU 1
+            let program = [
+                0x60, 0x08, 0x61, 0x04, 0xA2, 0x12, 0xD0, 0x11, 0x00, 0xC2, 0x00, 0xFB, 0x00, 0xFC,
+                0x00, 0xFC, 0x00, 0xD1, 0x80,
+            ];
+            let mut chip8 = run(Quirks::SUPER_CHIP, &program, 4);
+            assert_eq!(lit(&chip8), [(8, 4)]);
+
+            for expected in [(8, 6), (12, 6), (8, 6), (4, 6), (4, 5)] {
+                chip8.cycle().unwrap();
+                assert_eq!(lit(&chip8), [expected]);
+            }
+        }
+
+        #[test]
+        fn scrolling_drops_pixels_off_the_edge() {
+            // LD V0, 62; LD I, sprite; DRW V0, V1, 1; SCR
+            let program = [0x60, 0x3E, 0xA2, 0x08, 0xD0, 0x11, 0x00, 0xFB, 0xC0];
+            let mut chip8 = run(Quirks::SUPER_CHIP, &program, 3);
+            assert_eq!(lit(&chip8), [(62, 0), (63, 0)]);
+
+            chip8.cycle().unwrap();
+            assert!(lit(&chip8).is_empty());
+        }
+
+        #[test]
+        fn dxy0_draws_a_16x16_sprite_in_high_resolution() {
+            // HIGH; LD I, sprite; DRW V0, V0, 0; then a sprite with a full top row and sides
+            let mut program = vec![0x00, 0xFF, 0xA2, 0x06, 0xD0, 0x00];
+            program.extend([0xFF, 0xFF]);
+            for _ in 1..16 {
+                program.extend([0x80, 0x01]);
+            }
+            let chip8 = run(Quirks::SUPER_CHIP, &program, 3);
+
+            assert_eq!((chip8.width(), chip8.height()), (HIRES_WIDTH, HIRES_HEIGHT));
+            let mut expected: Vec<(usize, usize)> = (0..16).map(|x| (x, 0)).collect();
+            expected.extend((1..16).flat_map(|y| [(0, y), (15, y)]));
+            assert_eq!(lit(&chip8), expected);
+            assert_eq!(chip8.registers()[0xF], 0);
+        }
+
+        #[test]
+        fn planes_select_what_is_drawn_and_cleared() {
+            let program = [
+                0xF2, 0x01, // PLANE 2
+                0xA2, 0x20, // LD I, 0x220
+                0xD0, 0x01, // DRW V0, V0, 1
+                0xF3, 0x01, // PLANE 3
+                0x61, 0x08, // LD V1, 8
+                0xD1, 0x01, // DRW V1, V0, 1, one row for each plane
+                0xF1, 0x01, // PLANE 1
+                0x00, 0xE0, // CLS
+                0xF0, 0x01, // PLANE 0
+                0x00, 0xE0, // CLS
+                0xD1, 0x01, // DRW V1, V0, 1
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, //
+                0x80, 0x80, // 220: sprite rows for plane 1 and plane 2
+            ];
+            let mut chip8 = run(Quirks::XO_CHIP, &program, 3);
+            assert_eq!(chip8.plane_mask(), 0x2);
+            assert_eq!(chip8.gfx()[0], 0x2);
+
+            for _ in 0..3 {
+                chip8.cycle().unwrap();
+            }
+            assert_eq!(chip8.gfx()[8], 0x3);
+
+            for _ in 0..2 {
+                chip8.cycle().unwrap();
+            }
+            assert_eq!((chip8.gfx()[0], chip8.gfx()[8]), (0x2, 0x2));
+
+            for _ in 0..3 {
+                chip8.cycle().unwrap();
+            }
+            assert_eq!(chip8.plane_mask(), 0x0);
+            assert_eq!((chip8.gfx()[0], chip8.gfx()[8]), (0x2, 0x2));
+            assert_eq!(chip8.registers()[0xF], 0);
+            assert_eq!(lit(&chip8), [(0, 0), (8, 0)]);
+        }
+
+        #[test]
+        fn long_load_at_the_end_of_memory_wraps_the_pc() {
+            let mut chip8 = Chip8::new(Quirks::XO_CHIP);
+            chip8.write_memory(0xFFFC, &[0xF0, 0x00, 0x12, 0x34]);
+            chip8.set_pc(0xFFFC);
+
+            chip8.cycle().unwrap();
+
+            assert_eq!(chip8.ir(), 0x1234);
+            assert_eq!(chip8.pc(), 0x0000);
+        }
+
+        #[test]
+        fn instruction_at_the_end_of_memory_runs() {
+            let mut chip8 = Chip8::new(Quirks::XO_CHIP);
+            chip8.write_memory(0xFFFE, &[0x61, 0x07]);
+            chip8.set_pc(0xFFFE);
+
+            chip8.cycle().unwrap();
+
+            assert_eq!(chip8.registers()[1], 0x07);
+            assert_eq!(chip8.pc(), 0x0000);
+        }
+
+        #[test]
+        fn pc_past_the_last_instruction_is_an_error() {
+            let mut chip8 = Chip8::new(Quirks::XO_CHIP);
+            chip8.set_pc(0xFFFF);
+
+            assert!(matches!(
+                chip8.cycle(),
+                Err(Chip8Error::PcOutOfRange { pc: 0xFFFF })
+            ));
+        }
+
+        #[test]
+        fn long_load_without_its_address_word_is_an_error() {
+            let mut chip8 = Chip8::new(Quirks::XO_CHIP);
+            chip8.write_memory(0xFFFE, &[0xF0, 0x00]);
+            chip8.set_pc(0xFFFE);
+
+            assert!(matches!(
+                chip8.cycle(),
+                Err(Chip8Error::PcOutOfRange { pc: 0xFFFE })
+            ));
+            assert_eq!(chip8.pc(), 0xFFFE);
+        }
+
+        #[test]
+        fn load_rejects_a_state_returning_outside_of_memory() {
+            // CALL 0x204, leaving 0x200 in the first stack entry
+            let mut chip8 = Chip8::new(Quirks::XO_CHIP);
+            chip8.load_rom(&[0x22, 0x04]).unwrap();
+            chip8.cycle().unwrap();
+            let mut state = chip8.save_state();
+
+            // Magic, version, platform, quirks and opcode, then memory, V0 to VF, I and PC
+            let stack = 4 + 2 + 1 + 1 + 2 + MEMORY_SIZE + 16 + 2 + 2;
+            assert_eq!(state[stack..stack + 2], 0x200u16.to_le_bytes());
+            state[stack..stack + 2].copy_from_slice(&0xFFFFu16.to_le_bytes());
+
+            let mut target = Chip8::new(Quirks::XO_CHIP);
+            assert!(matches!(
+                target.load_state(&state),
+                Err(Chip8Error::InvalidState)
+            ));
+        }
+
+        #[test]
+        fn random_values_are_masked_by_nn() {
+            // RND V0, 0x0F; RND V1, 0xFF; RND V2, 0x00; RND V3, 0xA5
+            let program = [0xC0, 0x0F, 0xC1, 0xFF, 0xC2, 0x00, 0xC3, 0xA5];
+            let mut chip8 = Chip8::new(Quirks::default());
+            chip8.set_random_source(Box::new(ScriptedRandom::new(vec![0xFF, 0xFF, 0xFF, 0x3C])));
+            chip8.load_rom(&program).unwrap();
+            for _ in 0..4 {
+                chip8.cycle().unwrap();
+            }
+
+            assert_eq!(chip8.registers()[..4], [0x0F, 0xFF, 0x00, 0x3C & 0xA5]);
         }
     }
 }
@@ -0,0 +1,219 @@
+pub mod quirk_profile {
+    use std::str::FromStr;
+
+    /// Selects between the interpretations of the CHIP-8 instructions whose behaviour
+    /// differs between the historical interpreters
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct Quirks {
+        /// `8XY6`/`8XYE` shift VX in place, ignoring VY, instead of storing VY shifted into VX
+        pub shift: bool,
+        /// `FX55`/`FX65` leave I pointing past the last register stored or loaded
+        pub load_store: bool,
+        /// `BNNN` behaves as `BXNN`, jumping to XNN plus VX instead of NNN plus V0
+        pub jump: bool,
+        /// `8XY1`/`8XY2`/`8XY3` reset VF to 0
+        pub vf_reset: bool,
+        /// `DXYN` clips sprites at the screen edges instead of wrapping them around
+        pub clipping: bool,
+        /// `DXYN` waits for the next 60 Hz vertical blank before execution continues
+        pub display_wait: bool,
+    }
+
+    impl Quirks {
+        /// The interpretation this emulator used before quirks could be chosen, and still
+        /// the default: shifts in place, I advanced by loads and stores, no VF reset, sprites
+        /// wrapping around and no display wait
+        pub const LEGACY: Quirks = Quirks {
+            shift: true,
+            load_store: true,
+            jump: false,
+            vf_reset: false,
+            clipping: false,
+            display_wait: false,
+        };
+
+        /// The original COSMAC VIP interpreter
+        pub const COSMAC_VIP: Quirks = Quirks {
+            shift: false,
+            load_store: true,
+            jump: false,
+            vf_reset: true,
+            clipping: true,
+            display_wait: true,
+        };
+
+        /// SUPER-CHIP 1.1 on the HP48 calculators
+        pub const SUPER_CHIP: Quirks = Quirks {
+            shift: true,
+            load_store: false,
+            jump: true,
+            vf_reset: false,
+            clipping: true,
+            display_wait: false,
+        };
+
+        /// Octo's XO-CHIP
+        pub const XO_CHIP: Quirks = Quirks {
+            shift: false,
+            load_store: true,
+            jump: false,
+            vf_reset: false,
+            clipping: false,
+            display_wait: false,
+        };
+    }
+
+    impl Quirks {
+        /// Packs the quirks into one bit each, in field order
+        pub const fn to_bits(self) -> u8 {
+            self.shift as u8
+                | (self.load_store as u8) << 1
+                | (self.jump as u8) << 2
+                | (self.vf_reset as u8) << 3
+                | (self.clipping as u8) << 4
+                | (self.display_wait as u8) << 5
+        }
+
+        pub const fn from_bits(bits: u8) -> Quirks {
+            Quirks {
+                shift: bits & 1 != 0,
+                load_store: bits & 1 << 1 != 0,
+                jump: bits & 1 << 2 != 0,
+                vf_reset: bits & 1 << 3 != 0,
+                clipping: bits & 1 << 4 != 0,
+                display_wait: bits & 1 << 5 != 0,
+            }
+        }
+    }
+
+    impl Default for Quirks {
+        fn default() -> Self {
+            Quirks::LEGACY
+        }
+    }
+
+    impl FromStr for Quirks {
+        type Err = String;
+
+        fn from_str(name: &str) -> Result<Self, Self::Err> {
+            match name.to_ascii_lowercase().as_str() {
+                "vip" | "chip8" | "chip-8" => Ok(Quirks::COSMAC_VIP),
+                "schip" | "superchip" | "super-chip" => Ok(Quirks::SUPER_CHIP),
+                "xochip" | "xo-chip" => Ok(Quirks::XO_CHIP),
+                "legacy" => Ok(Quirks::LEGACY),
+                _ => Err(format!(
+                    "Unknown quirks profile \"{}\", expected legacy, vip, schip or xochip",
+                    name
+                )),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::chip8::processor::chip::Chip8;
+
+        // Runs the first `cycles` instructions of `program` with `quirks`
+        fn run(quirks: Quirks, program: &[u8], cycles: usize) -> Chip8 {
+            let mut chip8 = Chip8::new(quirks);
+            chip8.load_rom(program).unwrap();
+            for _ in 0..cycles {
+                chip8.cycle().unwrap();
+            }
+            chip8
+        }
+
+        // `quirks` with the one flag `set` turns on, and off
+        fn toggled(set: fn(&mut Quirks, bool)) -> (Quirks, Quirks) {
+            let (mut on, mut off) = (Quirks::LEGACY, Quirks::LEGACY);
+            set(&mut on, true);
+            set(&mut off, false);
+            (on, off)
+        }
+
+        #[test]
+        fn default_is_the_legacy_interpretation() {
+            assert_eq!(Quirks::default(), Quirks::LEGACY);
+            assert_eq!("legacy".parse(), Ok(Quirks::LEGACY));
+        }
+
+        #[test]
+        fn bits_round_trip() {
+            for quirks in [
+                Quirks::LEGACY,
+                Quirks::COSMAC_VIP,
+                Quirks::SUPER_CHIP,
+                Quirks::XO_CHIP,
+            ] {
+                assert_eq!(Quirks::from_bits(quirks.to_bits()), quirks);
+            }
+        }
+
+        #[test]
+        fn shift_selects_the_shifted_register() {
+            // LD V1, 5; LD V2, 3; SHR V1, V2
+            let program = [0x61, 0x05, 0x62, 0x03, 0x81, 0x26];
+            let (on, off) = toggled(|q, v| q.shift = v);
+            assert_eq!(run(on, &program, 3).registers()[1], 2);
+            assert_eq!(run(off, &program, 3).registers()[1], 1);
+        }
+
+        #[test]
+        fn load_store_advances_i() {
+            // LD I, 0x300; LD [I], V0
+            let program = [0xA3, 0x00, 0xF0, 0x55];
+            let (on, off) = toggled(|q, v| q.load_store = v);
+            assert_eq!(run(on, &program, 2).ir(), 0x301);
+            assert_eq!(run(off, &program, 2).ir(), 0x300);
+        }
+
+        #[test]
+        fn jump_selects_the_offset_register() {
+            // LD V0, 4; LD V1, 6; JP V0, 0x110
+            let program = [0x60, 0x04, 0x61, 0x06, 0xB1, 0x10];
+            let (on, off) = toggled(|q, v| q.jump = v);
+            assert_eq!(run(on, &program, 3).pc(), 0x116);
+            assert_eq!(run(off, &program, 3).pc(), 0x114);
+        }
+
+        #[test]
+        fn vf_reset_clears_vf_on_logic() {
+            // LD VF, 5; OR V0, V1
+            let program = [0x6F, 0x05, 0x80, 0x11];
+            let (on, off) = toggled(|q, v| q.vf_reset = v);
+            assert_eq!(run(on, &program, 2).registers()[0xF], 0);
+            assert_eq!(run(off, &program, 2).registers()[0xF], 5);
+        }
+
+        #[test]
+        fn clipping_stops_sprites_at_the_edge() {
+            // LD V0, 62; LD V1, 0; LD I, 0x208; DRW V0, V1, 1; a full row sprite
+            let program = [0x60, 0x3E, 0x61, 0x00, 0xA2, 0x08, 0xD0, 0x11, 0xFF];
+            let (on, off) = toggled(|q, v| q.clipping = v);
+
+            let clipped = run(on, &program, 4);
+            assert_eq!(clipped.gfx()[62..64], [1, 1]);
+            assert_eq!(clipped.gfx()[0..6], [0; 6]);
+            let wrapped = run(off, &program, 4);
+            assert_eq!(wrapped.gfx()[62..64], [1, 1]);
+            assert_eq!(wrapped.gfx()[0..6], [1; 6]);
+        }
+
+        #[test]
+        fn display_wait_holds_execution_until_the_vertical_blank() {
+            // LD I, 0x206; DRW V0, V0, 1; LD V0, 5; sprite
+            let program = [0xA2, 0x06, 0xD0, 0x01, 0x60, 0x05, 0x80];
+            let (on, off) = toggled(|q, v| q.display_wait = v);
+
+            let mut waiting = run(on, &program, 3);
+            assert!(waiting.is_waiting_vblank());
+            assert_eq!(waiting.registers()[0], 0);
+            waiting.tick_timers();
+            waiting.cycle().unwrap();
+            assert_eq!(waiting.registers()[0], 5);
+
+            assert_eq!(run(off, &program, 3).registers()[0], 5);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+pub mod rewind_buffer {
+    use std::collections::VecDeque;
+
+    /// Default number of frames of history, 10 seconds at 60 Hz
+    pub const DEFAULT_REWIND_FRAMES: usize = 600;
+    /// Default cap on the memory used by the history, in bytes
+    pub const DEFAULT_REWIND_MEMORY: usize = 64 * 1024 * 1024;
+
+    // Differing runs separated by fewer equal bytes than this are merged into one
+    const MERGE_GAP: usize = 8;
+    // Offset and length prefixed to every run
+    const RUN_HEADER: usize = 6;
+    const MAX_RUN: usize = u16::MAX as usize;
+
+    /// Bounded history of save states, one per frame. Only the latest state is kept whole,
+    /// every older one is stored as the bytes that differ from the state after it
+    pub struct Rewind {
+        head: Option<Vec<u8>>,
+        deltas: VecDeque<Vec<u8>>,
+        max_frames: usize,
+        max_bytes: usize,
+        bytes: usize,
+    }
+
+    impl Rewind {
+        /// History of at most `max_frames` states using at most `max_bytes` for the deltas
+        pub fn new(max_frames: usize, max_bytes: usize) -> Rewind {
+            Rewind {
+                head: None,
+                deltas: VecDeque::new(),
+                max_frames,
+                max_bytes,
+                bytes: 0,
+            }
+        }
+
+        /// Records the state of the latest frame, dropping the oldest frames over the limits
+        pub fn push(&mut self, state: Vec<u8>) {
+            if self.max_frames == 0 {
+                return;
+            }
+
+            if let Some(head) = self.head.take() {
+                if head.len() == state.len() {
+                    let delta = delta(&state, &head);
+                    self.bytes += delta.len();
+                    self.deltas.push_back(delta);
+                } else {
+                    self.clear();
+                }
+            }
+            self.head = Some(state);
+
+            while self.deltas.len() >= self.max_frames || self.bytes > self.max_bytes {
+                match self.deltas.pop_front() {
+                    Some(oldest) => self.bytes -= oldest.len(),
+                    None => break,
+                }
+            }
+        }
+
+        /// Steps one frame back, returning the state before the latest one
+        pub fn pop(&mut self) -> Option<&[u8]> {
+            let delta = self.deltas.pop_back()?;
+            self.bytes -= delta.len();
+
+            let head = self.head.as_mut()?;
+            apply(head, &delta);
+            Some(head)
+        }
+
+        /// Number of frames that can be stepped back
+        pub fn len(&self) -> usize {
+            self.deltas.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.deltas.is_empty()
+        }
+
+        pub fn clear(&mut self) {
+            self.head = None;
+            self.deltas.clear();
+            self.bytes = 0;
+        }
+    }
+
+    // Encodes the runs of bytes where `from` differs from `to`, holding the `to` values,
+    // as a little endian u32 offset and u16 length followed by the bytes
+    fn delta(from: &[u8], to: &[u8]) -> Vec<u8> {
+        let mut delta = Vec::new();
+        let mut i = 0;
+
+        while i < from.len() {
+            if from[i] == to[i] {
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+            let mut end = i + 1;
+            let mut equal = 0;
+            i += 1;
+            while i < from.len() && i - start < MAX_RUN && equal < MERGE_GAP {
+                if from[i] == to[i] {
+                    equal += 1;
+                } else {
+                    equal = 0;
+                    end = i + 1;
+                }
+                i += 1;
+            }
+
+            delta.extend_from_slice(&(start as u32).to_le_bytes());
+            delta.extend_from_slice(&((end - start) as u16).to_le_bytes());
+            delta.extend_from_slice(&to[start..end]);
+            i = end;
+        }
+
+        delta
+    }
+
+    // Writes the runs of a delta over `state`
+    fn apply(state: &mut [u8], delta: &[u8]) {
+        let mut i = 0;
+        while i + RUN_HEADER <= delta.len() {
+            let offset =
+                u32::from_le_bytes([delta[i], delta[i + 1], delta[i + 2], delta[i + 3]]) as usize;
+            let len = u16::from_le_bytes([delta[i + 4], delta[i + 5]]) as usize;
+            i += RUN_HEADER;
+            state[offset..offset + len].copy_from_slice(&delta[i..i + len]);
+            i += len;
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::chip8::{processor::chip::Chip8, quirks::quirk_profile::Quirks};
+
+        // States the size of a save state, each changing scattered bytes, short runs and a run
+        // longer than MAX_RUN
+        fn states(count: usize) -> Vec<Vec<u8>> {
+            let mut state = vec![0u8; 0x12000];
+            (0..count)
+                .map(|frame| {
+                    let scattered = frame * 7919 % state.len();
+                    state[scattered] ^= 0xFF;
+                    state[100 + frame % 5] = frame as u8;
+                    state[110 + frame % 3] = !(frame as u8);
+                    if frame % 4 == 0 {
+                        state[0x1000..0x1000 + MAX_RUN + 100].fill(frame as u8);
+                    }
+                    state.clone()
+                })
+                .collect()
+        }
+
+        #[test]
+        fn pop_returns_every_pushed_state() {
+            let states = states(50);
+            let mut rewind = Rewind::new(100, usize::MAX);
+            for state in &states {
+                rewind.push(state.clone());
+            }
+            assert_eq!(rewind.len(), 49);
+
+            for expected in states.iter().rev().skip(1) {
+                assert_eq!(rewind.pop(), Some(expected.as_slice()));
+            }
+            assert!(rewind.is_empty());
+            assert_eq!(rewind.pop(), None);
+        }
+
+        #[test]
+        fn pop_returns_the_states_of_a_running_machine() {
+            // Counts V0 up every instruction and stores it to memory through a moving I
+            let program = [0x70, 0x01, 0xA3, 0x00, 0xF0, 0x1E, 0xF0, 0x55, 0x12, 0x00];
+            let mut chip8 = Chip8::new(Quirks::SUPER_CHIP);
+            chip8.load_rom(&program).unwrap();
+
+            let mut rewind = Rewind::new(DEFAULT_REWIND_FRAMES, DEFAULT_REWIND_MEMORY);
+            let mut states = Vec::new();
+            for _ in 0..30 {
+                for _ in 0..9 {
+                    chip8.cycle().unwrap();
+                }
+                chip8.tick_timers();
+                states.push(chip8.save_state());
+                rewind.push(chip8.save_state());
+            }
+
+            for expected in states.iter().rev().skip(1) {
+                let state = rewind.pop().unwrap();
+                assert_eq!(state, expected.as_slice());
+                chip8.load_state(state).unwrap();
+            }
+            assert_eq!(chip8.save_state(), states[0]);
+        }
+
+        #[test]
+        fn oldest_frames_are_dropped_over_the_limits() {
+            let states = states(20);
+
+            let mut rewind = Rewind::new(5, usize::MAX);
+            for state in &states {
+                rewind.push(state.clone());
+            }
+            assert_eq!(rewind.len(), 4);
+            for expected in states.iter().rev().skip(1).take(4) {
+                assert_eq!(rewind.pop(), Some(expected.as_slice()));
+            }
+            assert_eq!(rewind.pop(), None);
+
+            let mut rewind = Rewind::new(100, 0);
+            for state in &states {
+                rewind.push(state.clone());
+            }
+            assert!(rewind.is_empty());
+        }
+    }
+}
@@ -0,0 +1,341 @@
+pub mod screen_image {
+    use crate::chip8::processor::chip::Chip8;
+    use std::{fs, fs::File, io::BufWriter, path::Path};
+
+    // ASCII art characters for the pixel values, background first
+    const ASCII_PIXELS: [char; 4] = ['.', '#', '+', '@'];
+
+    /// A copy of the framebuffer that can be stored as a PBM, PNG or ASCII art image and
+    /// compared against a reference
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Screen {
+        width: usize,
+        height: usize,
+        // Bit plane values, 0 for the background
+        pixels: Vec<u8>,
+        // Loaded from a format that only tells set pixels from clear ones
+        monochrome: bool,
+    }
+
+    impl Screen {
+        pub fn capture(chip8: &Chip8) -> Screen {
+            Screen {
+                width: chip8.width(),
+                height: chip8.height(),
+                pixels: chip8.gfx().to_vec(),
+                monochrome: false,
+            }
+        }
+
+        pub const fn width(&self) -> usize {
+            self.width
+        }
+
+        pub const fn height(&self) -> usize {
+            self.height
+        }
+
+        /// Plain PBM, one digit per pixel
+        pub fn to_pbm(&self) -> String {
+            let mut text = format!("P1\n{} {}\n", self.width, self.height);
+            for row in self.pixels.chunks(self.width) {
+                let row: Vec<&str> = row
+                    .iter()
+                    .map(|&p| if p != 0 { "1" } else { "0" })
+                    .collect();
+                text.push_str(&row.join(" "));
+                text.push('\n');
+            }
+            text
+        }
+
+        /// One character per pixel, `.` for the background and `#`, `+` and `@` for planes 1, 2
+        /// and both
+        pub fn to_ascii(&self) -> String {
+            let mut text = String::new();
+            for row in self.pixels.chunks(self.width) {
+                text.extend(row.iter().map(|&p| ASCII_PIXELS[p as usize % 4]));
+                text.push('\n');
+            }
+            text
+        }
+
+        pub fn from_pbm(text: &str) -> Result<Screen, String> {
+            // Comments run from # to the end of the line
+            let mut fields = text
+                .lines()
+                .map(|line| line.split('#').next().unwrap_or_default())
+                .flat_map(str::split_whitespace);
+
+            if fields.next() != Some("P1") {
+                return Err("Not a plain PBM image".to_string());
+            }
+            let mut dimension = || -> Result<usize, String> {
+                fields
+                    .next()
+                    .and_then(|f| f.parse().ok())
+                    .ok_or_else(|| "Invalid PBM size".to_string())
+            };
+            let (width, height) = (dimension()?, dimension()?);
+
+            // Pixels may be written without separators
+            let pixels: Vec<u8> = fields
+                .flat_map(str::chars)
+                .map(|c| match c {
+                    '0' => Ok(0),
+                    '1' => Ok(1),
+                    _ => Err(format!("Invalid PBM pixel '{}'", c)),
+                })
+                .collect::<Result<_, _>>()?;
+
+            Self::from_pixels(width, height, pixels, true)
+        }
+
+        pub fn from_ascii(text: &str) -> Result<Screen, String> {
+            let rows: Vec<&str> = text.lines().filter(|row| !row.is_empty()).collect();
+            let width = rows.first().map_or(0, |row| row.chars().count());
+
+            let mut pixels = Vec::new();
+            for row in &rows {
+                if row.chars().count() != width {
+                    return Err("ASCII art rows differ in width".to_string());
+                }
+                for c in row.chars() {
+                    let value = ASCII_PIXELS
+                        .iter()
+                        .position(|&p| p == c)
+                        .ok_or_else(|| format!("Invalid ASCII art pixel '{}'", c))?;
+                    pixels.push(value as u8);
+                }
+            }
+
+            Self::from_pixels(width, rows.len(), pixels, false)
+        }
+
+        /// Reads a PNG written by `save_png`, `palette` mapping the colours back to pixels
+        pub fn load_png(path: &Path, palette: [u32; 4]) -> Result<Screen, String> {
+            let file = File::open(path).map_err(|e| e.to_string())?;
+            let mut decoder = png::Decoder::new(file);
+            decoder.set_transformations(png::Transformations::EXPAND);
+            let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+            let mut data = vec![0; reader.output_buffer_size()];
+            let info = reader.next_frame(&mut data).map_err(|e| e.to_string())?;
+
+            let channels = info.color_type.samples();
+            if info.bit_depth != png::BitDepth::Eight || channels < 3 {
+                return Err("Only 8 bit colour PNGs can be compared".to_string());
+            }
+
+            let pixels = data[..info.buffer_size()]
+                .chunks(channels)
+                .map(|rgb| {
+                    let colour = (rgb[0] as u32) << 16 | (rgb[1] as u32) << 8 | rgb[2] as u32;
+                    palette
+                        .iter()
+                        .position(|&c| c == colour)
+                        .map(|p| p as u8)
+                        .ok_or_else(|| format!("Colour {:06X} is not in the palette", colour))
+                })
+                .collect::<Result<_, _>>()?;
+
+            Self::from_pixels(info.width as usize, info.height as usize, pixels, false)
+        }
+
+        /// Writes an RGB PNG with one image pixel per CHIP-8 pixel, coloured from `palette`
+        pub fn save_png(&self, path: &Path, palette: [u32; 4]) -> Result<(), String> {
+            let file = File::create(path).map_err(|e| e.to_string())?;
+            let mut encoder =
+                png::Encoder::new(BufWriter::new(file), self.width as u32, self.height as u32);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+
+            let data: Vec<u8> = self
+                .pixels
+                .iter()
+                .flat_map(|&p| {
+                    let colour = palette[p as usize % palette.len()];
+                    [(colour >> 16) as u8, (colour >> 8) as u8, colour as u8]
+                })
+                .collect();
+
+            encoder
+                .write_header()
+                .and_then(|mut writer| writer.write_image_data(&data))
+                .map_err(|e| e.to_string())
+        }
+
+        /// Reads an image, picking the format from the extension: `.pbm`, `.png`, or ASCII art
+        /// for anything else
+        pub fn load(path: &Path, palette: [u32; 4]) -> Result<Screen, String> {
+            let read = || fs::read_to_string(path).map_err(|e| e.to_string());
+            match extension(path).as_str() {
+                "pbm" => Self::from_pbm(&read()?),
+                "png" => Self::load_png(path, palette),
+                _ => Self::from_ascii(&read()?),
+            }
+        }
+
+        /// Writes an image, picking the format like `load`
+        pub fn save(&self, path: &Path, palette: [u32; 4]) -> Result<(), String> {
+            match extension(path).as_str() {
+                "pbm" => fs::write(path, self.to_pbm()).map_err(|e| e.to_string()),
+                "png" => self.save_png(path, palette),
+                _ => fs::write(path, self.to_ascii()).map_err(|e| e.to_string()),
+            }
+        }
+
+        /// Number of pixels that differ from `reference`, or `None` when the sizes differ.
+        /// Against monochrome references only set and clear pixels are told apart
+        pub fn differences(&self, reference: &Screen) -> Option<usize> {
+            if self.width != reference.width || self.height != reference.height {
+                return None;
+            }
+
+            let monochrome = self.monochrome || reference.monochrome;
+            let differences = self
+                .pixels
+                .iter()
+                .zip(&reference.pixels)
+                .filter(|(&a, &b)| {
+                    if monochrome {
+                        (a != 0) != (b != 0)
+                    } else {
+                        a != b
+                    }
+                })
+                .count();
+            Some(differences)
+        }
+
+        /// Succeeds when the screen matches `reference`, otherwise says how they differ
+        pub fn compare(&self, reference: &Screen) -> Result<(), String> {
+            match self.differences(reference) {
+                Some(0) => Ok(()),
+                Some(count) => Err(format!("Screen differs in {} pixels", count)),
+                None => Err(format!(
+                    "Screen is {}x{} but the reference is {}x{}",
+                    self.width, self.height, reference.width, reference.height
+                )),
+            }
+        }
+
+        fn from_pixels(
+            width: usize,
+            height: usize,
+            pixels: Vec<u8>,
+            monochrome: bool,
+        ) -> Result<Screen, String> {
+            if width == 0 || pixels.len() != width * height {
+                return Err(format!(
+                    "Expected {}x{} pixels, found {}",
+                    width,
+                    height,
+                    pixels.len()
+                ));
+            }
+            Ok(Screen {
+                width,
+                height,
+                pixels,
+                monochrome,
+            })
+        }
+    }
+
+    fn extension(path: &Path) -> String {
+        path.extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const ART: &str = ".#+@\n@+#.\n";
+
+        #[test]
+        fn ascii_round_trips() {
+            let screen = Screen::from_ascii(ART).unwrap();
+            assert_eq!((screen.width(), screen.height()), (4, 2));
+            assert_eq!(screen.to_ascii(), ART);
+        }
+
+        #[test]
+        fn pbm_round_trips_set_pixels() {
+            let screen = Screen::from_ascii(ART).unwrap();
+            let pbm = screen.to_pbm();
+            assert_eq!(pbm, "P1\n4 2\n0 1 1 1\n1 1 1 0\n");
+
+            let loaded = Screen::from_pbm(&pbm).unwrap();
+            assert_eq!(loaded.to_ascii(), ".###\n###.\n");
+            // Only set and clear pixels are compared against a PBM
+            assert_eq!(screen.differences(&loaded), Some(0));
+        }
+
+        #[test]
+        fn pbm_parser_skips_comments_and_accepts_packed_pixels() {
+            let screen = Screen::from_pbm("P1 # plain\n# size\n4 2\n0111\n1110 # end\n").unwrap();
+            assert_eq!(screen.to_pbm(), "P1\n4 2\n0 1 1 1\n1 1 1 0\n");
+
+            assert_eq!(
+                Screen::from_pbm("P4\n4 2\n"),
+                Err("Not a plain PBM image".to_string())
+            );
+            assert_eq!(
+                Screen::from_pbm("P1\n4 2\n0101\n"),
+                Err("Expected 4x2 pixels, found 4".to_string())
+            );
+            assert_eq!(
+                Screen::from_pbm("P1\n1 1\n2\n"),
+                Err("Invalid PBM pixel '2'".to_string())
+            );
+            assert!(Screen::from_ascii("..\n.\n").is_err());
+        }
+
+        #[test]
+        fn png_round_trips() {
+            let palette = [0x000000, 0xFFFFFF, 0xFF0000, 0x00FF00];
+            let path =
+                std::env::temp_dir().join(format!("chip8-screen-{}.png", std::process::id()));
+            let screen = Screen::from_ascii(ART).unwrap();
+
+            screen.save(&path, palette).unwrap();
+            let loaded = Screen::load(&path, palette);
+            let other_palette = Screen::load(&path, [0x000000, 0xFFFFFF, 0xFF0000, 0x0000FF]);
+            fs::remove_file(&path).unwrap();
+
+            assert_eq!(loaded, Ok(screen));
+            assert_eq!(
+                other_palette,
+                Err("Colour 00FF00 is not in the palette".to_string())
+            );
+        }
+
+        #[test]
+        fn differences_count_pixels() {
+            let screen = Screen::from_ascii(ART).unwrap();
+            let changed = Screen::from_ascii(".#+.\n@@#.\n").unwrap();
+            assert_eq!(screen.differences(&changed), Some(2));
+            assert_eq!(screen.differences(&screen), Some(0));
+            assert_eq!(
+                screen.differences(&Screen::from_ascii("...\n").unwrap()),
+                None
+            );
+        }
+
+        #[test]
+        fn mismatched_references_are_errors() {
+            let screen = Screen::from_ascii(ART).unwrap();
+            assert_eq!(screen.compare(&screen.clone()), Ok(()));
+            assert_eq!(
+                screen.compare(&Screen::from_ascii("....\n@+#.\n").unwrap()),
+                Err("Screen differs in 3 pixels".to_string())
+            );
+            assert_eq!(
+                screen.compare(&Screen::from_ascii("..\n").unwrap()),
+                Err("Screen is 4x2 but the reference is 2x1".to_string())
+            );
+        }
+    }
+}
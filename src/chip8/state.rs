@@ -0,0 +1,207 @@
+pub mod save_state {
+    use crate::chip8::error::chip_error::Chip8Error;
+    use std::path::{Path, PathBuf};
+
+    /// Identifies a save state file
+    pub const MAGIC: &[u8; 4] = b"CH8S";
+    /// Save state format version, bumped whenever the layout changes
+    pub const VERSION: u16 = 1;
+    /// Number of save slots bound to hotkeys
+    pub const SLOTS: u8 = 9;
+
+    // Builds a save state in little endian field order
+    pub(crate) struct StateWriter {
+        data: Vec<u8>,
+    }
+
+    impl StateWriter {
+        pub(crate) fn new() -> StateWriter {
+            let mut writer = StateWriter { data: Vec::new() };
+            writer.bytes(MAGIC);
+            writer.u16(VERSION);
+            writer
+        }
+
+        pub(crate) fn u8(&mut self, value: u8) {
+            self.data.push(value);
+        }
+
+        pub(crate) fn u16(&mut self, value: u16) {
+            self.data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        pub(crate) fn bytes(&mut self, values: &[u8]) {
+            self.data.extend_from_slice(values);
+        }
+
+        pub(crate) fn finish(self) -> Vec<u8> {
+            self.data
+        }
+    }
+
+    // Reads back the fields written by a StateWriter, in the same order
+    pub(crate) struct StateReader<'a> {
+        data: &'a [u8],
+    }
+
+    impl<'a> StateReader<'a> {
+        pub(crate) fn new(data: &'a [u8]) -> Result<StateReader<'a>, Chip8Error> {
+            let mut reader = StateReader { data };
+            if reader.bytes(MAGIC.len())? != MAGIC {
+                return Err(Chip8Error::InvalidState);
+            }
+
+            let version = reader.u16()?;
+            if version != VERSION {
+                return Err(Chip8Error::IncompatibleState {
+                    version,
+                    expected: VERSION,
+                });
+            }
+
+            Ok(reader)
+        }
+
+        pub(crate) fn u8(&mut self) -> Result<u8, Chip8Error> {
+            Ok(self.bytes(1)?[0])
+        }
+
+        pub(crate) fn u16(&mut self) -> Result<u16, Chip8Error> {
+            let bytes = self.bytes(2)?;
+            Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+        }
+
+        pub(crate) fn bool(&mut self) -> Result<bool, Chip8Error> {
+            Ok(self.u8()? != 0)
+        }
+
+        pub(crate) fn bytes(&mut self, len: usize) -> Result<&'a [u8], Chip8Error> {
+            if self.data.len() < len {
+                return Err(Chip8Error::InvalidState);
+            }
+            let (bytes, rest) = self.data.split_at(len);
+            self.data = rest;
+            Ok(bytes)
+        }
+
+        pub(crate) fn array<const N: usize>(&mut self) -> Result<[u8; N], Chip8Error> {
+            let mut array = [0; N];
+            array.copy_from_slice(self.bytes(N)?);
+            Ok(array)
+        }
+    }
+
+    /// Locates the numbered save slots of a ROM, named after the ROM file and stored in `dir`
+    pub struct SaveSlots {
+        dir: PathBuf,
+        name: String,
+    }
+
+    impl SaveSlots {
+        /// Slots stored next to the ROM at `rom_path`
+        pub fn beside(rom_path: &Path) -> SaveSlots {
+            let dir = rom_path.parent().unwrap_or_else(|| Path::new("."));
+            Self::in_dir(dir, rom_path)
+        }
+
+        /// Slots for the ROM at `rom_path` stored in `dir`
+        pub fn in_dir(dir: &Path, rom_path: &Path) -> SaveSlots {
+            SaveSlots {
+                dir: dir.to_path_buf(),
+                name: rom_path
+                    .file_name()
+                    .map_or_else(|| "rom".to_string(), |n| n.to_string_lossy().to_string()),
+            }
+        }
+
+        /// Path of the save state in `slot`
+        pub fn path(&self, slot: u8) -> PathBuf {
+            self.dir.join(format!("{}.{}.state", self.name, slot))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::chip8::{processor::chip::Chip8, quirks::quirk_profile::Quirks};
+
+        // Draws a sprite, then calls a subroutine that sets the delay timer and loops there
+        const PROGRAM: [u8; 25] = [
+            0x6A, 0x05, // 200: LD VA, 5
+            0x6B, 0x0C, // 202: LD VB, 12
+            0xA2, 0x16, // 204: LD I, 216
+            0xDA, 0xB3, // 206: DRW VA, VB, 3
+            0x22, 0x10, // 208: CALL 210
+            0x12, 0x0A, // 20A: JP 20A
+            0x00, 0x00, 0x00, 0x00, //
+            0x65, 0x07, // 210: LD V5, 7
+            0xF5, 0x15, // 212: LD DT, V5
+            0x00, 0xEE, // 214: RET
+            0xF0, 0x90, 0xF0, // 216: sprite
+        ];
+
+        // A machine partway through PROGRAM, inside the subroutine with a key held
+        fn running_machine() -> Chip8 {
+            let mut chip8 = Chip8::new(Quirks::SUPER_CHIP);
+            chip8.load_rom(&PROGRAM).unwrap();
+            for _ in 0..7 {
+                chip8.cycle().unwrap();
+            }
+            chip8.set_action(0xA, true);
+            chip8
+        }
+
+        #[test]
+        fn load_restores_the_saved_machine() {
+            let original = running_machine();
+            let state = original.save_state();
+
+            let mut restored = Chip8::new(Quirks::COSMAC_VIP);
+            restored.load_state(&state).unwrap();
+
+            assert_eq!(restored.save_state(), state);
+            assert_eq!(restored.quirks(), Quirks::SUPER_CHIP);
+            assert_eq!(restored.pc(), original.pc());
+            assert_eq!(restored.ir(), 0x216);
+            assert_eq!(restored.registers(), original.registers());
+            assert_eq!(restored.stack(), original.stack());
+            assert_eq!(restored.sp(), 1);
+            assert_eq!(restored.delay_timer(), 7);
+            assert_eq!(restored.memory(), original.memory());
+            assert_eq!(restored.gfx(), original.gfx());
+            assert!(restored.is_key_pressed(0xA));
+        }
+
+        #[test]
+        fn load_rejects_another_version() {
+            let mut state = running_machine().save_state();
+            state[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&(VERSION + 1).to_le_bytes());
+
+            let mut chip8 = Chip8::new(Quirks::COSMAC_VIP);
+            let before = chip8.save_state();
+
+            assert!(matches!(
+                chip8.load_state(&state),
+                Err(Chip8Error::IncompatibleState { version, expected: VERSION })
+                    if version == VERSION + 1
+            ));
+            assert_eq!(chip8.save_state(), before);
+        }
+
+        #[test]
+        fn load_rejects_truncated_data() {
+            let state = running_machine().save_state();
+
+            let mut chip8 = Chip8::new(Quirks::COSMAC_VIP);
+            let before = chip8.save_state();
+
+            for len in [0, 3, MAGIC.len() + 2, state.len() / 2, state.len() - 1] {
+                assert!(matches!(
+                    chip8.load_state(&state[..len]),
+                    Err(Chip8Error::InvalidState)
+                ));
+                assert_eq!(chip8.save_state(), before);
+            }
+        }
+    }
+}
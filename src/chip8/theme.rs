@@ -0,0 +1,77 @@
+pub mod colour_theme {
+    use std::str::FromStr;
+
+    /// A named palette, the background colour followed by the colours of plane 1, plane 2 and
+    /// both planes as `0xRRGGBB`
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct Theme {
+        pub name: &'static str,
+        pub palette: [u32; 4],
+    }
+
+    impl Theme {
+        /// White on black, like the COSMAC VIP on a television
+        pub const CLASSIC: Theme = Theme {
+            name: "classic",
+            palette: [0x000000, 0xFFFFFF, 0xAAAAAA, 0x555555],
+        };
+
+        /// Dark green on a pale green LCD
+        pub const LCD: Theme = Theme {
+            name: "lcd",
+            palette: [0x9BBC0F, 0x0F380F, 0x306230, 0x8BAC0F],
+        };
+
+        /// An amber monochrome monitor
+        pub const AMBER: Theme = Theme {
+            name: "amber",
+            palette: [0x1A0F00, 0xFFB000, 0xCC7A00, 0x7F4A00],
+        };
+
+        /// Saturated colours on black, each plane easy to tell apart
+        pub const HIGH_CONTRAST: Theme = Theme {
+            name: "contrast",
+            palette: [0x000000, 0xFFFFFF, 0xFFFF00, 0x00FFFF],
+        };
+
+        /// The built-in themes, in the order the theme hotkey cycles through them
+        pub const ALL: [Theme; 4] = [
+            Theme::CLASSIC,
+            Theme::LCD,
+            Theme::AMBER,
+            Theme::HIGH_CONTRAST,
+        ];
+
+        /// The theme after the one using `palette`, or the first for a palette of no theme
+        pub fn after(palette: [u32; 4]) -> Theme {
+            let next = Theme::ALL
+                .iter()
+                .position(|theme| theme.palette == palette)
+                .map_or(0, |i| (i + 1) % Theme::ALL.len());
+            Theme::ALL[next]
+        }
+    }
+
+    impl Default for Theme {
+        fn default() -> Self {
+            Theme::CLASSIC
+        }
+    }
+
+    impl FromStr for Theme {
+        type Err = String;
+
+        fn from_str(name: &str) -> Result<Self, Self::Err> {
+            match name.to_ascii_lowercase().as_str() {
+                "classic" | "vip" => Ok(Theme::CLASSIC),
+                "lcd" | "green" => Ok(Theme::LCD),
+                "amber" => Ok(Theme::AMBER),
+                "contrast" | "high-contrast" => Ok(Theme::HIGH_CONTRAST),
+                _ => Err(format!(
+                    "Unknown theme \"{}\", expected classic, lcd, amber or contrast",
+                    name
+                )),
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+pub mod instruction_trace {
+    use crate::chip8::{
+        disassembler::disassembly::{disassemble, Syntax},
+        processor::chip::Chip8,
+    };
+    use std::{
+        fs::File,
+        io::{self, BufWriter, Write},
+        path::Path,
+    };
+
+    /// Writes a line for every executed instruction, with the state of the machine before it
+    /// runs, in a fixed column format suitable for diffing
+    ///
+    /// ```text
+    /// 0000000001 0200 6005 V=00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 I=0000 SP=0 DT=00 ST=00 LD V0, 0x05
+    /// ```
+    ///
+    /// The columns are the instruction count, PC, the opcode, V0 to VF, I, SP, the delay and
+    /// sound timers and the disassembly
+    pub struct Trace {
+        output: Box<dyn Write + Send>,
+        start: u16,
+        end: u16,
+        limit: Option<u64>,
+        cycles: u64,
+        lines: u64,
+    }
+
+    impl Trace {
+        /// Traces every instruction to `output`
+        pub fn new(output: Box<dyn Write + Send>) -> Trace {
+            Trace {
+                output,
+                start: 0,
+                end: u16::MAX,
+                limit: None,
+                cycles: 0,
+                lines: 0,
+            }
+        }
+
+        pub fn stdout() -> Trace {
+            Self::new(Box::new(io::stdout()))
+        }
+
+        pub fn to_file(path: &Path) -> io::Result<Trace> {
+            Ok(Self::new(Box::new(BufWriter::new(File::create(path)?))))
+        }
+
+        /// Only traces the instructions at addresses from `start` to `end` inclusive
+        pub fn set_range(&mut self, start: u16, end: u16) {
+            self.start = start;
+            self.end = end;
+        }
+
+        /// Stops tracing after `limit` lines
+        pub fn set_limit(&mut self, limit: Option<u64>) {
+            self.limit = limit;
+        }
+
+        /// Whether the line limit was reached
+        pub fn is_finished(&self) -> bool {
+            self.limit.is_some_and(|limit| self.lines >= limit)
+        }
+
+        /// Records the instruction `chip8` is about to execute
+        pub fn record(&mut self, chip8: &Chip8) -> io::Result<()> {
+            if chip8.is_waiting_vblank() || chip8.has_exited() {
+                return Ok(());
+            }
+
+            self.cycles += 1;
+            let pc = chip8.pc();
+            if pc < self.start || pc > self.end || self.is_finished() {
+                return Ok(());
+            }
+
+            let memory = chip8.memory();
+            let opcode = (memory[pc as usize] as u16) << 8
+                | *memory.get(pc as usize + 1).unwrap_or(&0) as u16;
+            let (text, _) = disassemble(memory, pc as usize, chip8.platform(), Syntax::Classic);
+            let registers: Vec<String> = chip8
+                .registers()
+                .iter()
+                .map(|v| format!("{:02X}", v))
+                .collect();
+
+            writeln!(
+                self.output,
+                "{:010} {:04X} {:04X} V={} I={:04X} SP={:X} DT={:02X} ST={:02X} {}",
+                self.cycles,
+                pc,
+                opcode,
+                registers.join(" "),
+                chip8.ir(),
+                chip8.sp(),
+                chip8.delay_timer(),
+                chip8.sound_timer(),
+                text
+            )?;
+
+            self.lines += 1;
+            if self.is_finished() {
+                self.output.flush()?;
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::chip8::quirks::quirk_profile::Quirks;
+        use std::sync::{Arc, Mutex};
+
+        // A writer the test keeps reading after handing it to the trace
+        #[derive(Clone, Default)]
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Shared {
+            fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(data);
+                Ok(data.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        // Traces `cycles` instructions of `program`, returning the lines written
+        fn trace(program: &[u8], cycles: usize, configure: impl Fn(&mut Trace)) -> Vec<String> {
+            let output = Shared::default();
+            let mut trace = Trace::new(Box::new(output.clone()));
+            configure(&mut trace);
+
+            let mut chip8 = Chip8::new(Quirks::default());
+            chip8.load_rom(program).unwrap();
+            for _ in 0..cycles {
+                trace.record(&chip8).unwrap();
+                chip8.cycle().unwrap();
+            }
+
+            let text = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+            text.lines().map(str::to_string).collect()
+        }
+
+        // LD V0, 5; LD I, 0x2F0; ADD V0, 1; JP 0x204
+        const PROGRAM: [u8; 8] = [0x60, 0x05, 0xA2, 0xF0, 0x70, 0x01, 0x12, 0x04];
+
+        #[test]
+        fn lines_have_fixed_columns() {
+            let lines = trace(&PROGRAM, 3, |_| {});
+            assert_eq!(
+                lines,
+                [
+                    "0000000001 0200 6005 V=00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 \
+                     I=0000 SP=0 DT=00 ST=00 LD V0, 0x05",
+                    "0000000002 0202 A2F0 V=05 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 \
+                     I=0000 SP=0 DT=00 ST=00 LD I, 0x2F0",
+                    "0000000003 0204 7001 V=05 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 \
+                     I=02F0 SP=0 DT=00 ST=00 ADD V0, 0x01",
+                ]
+            );
+        }
+
+        #[test]
+        fn range_filters_lines_but_keeps_counting() {
+            let lines = trace(&PROGRAM, 7, |trace| trace.set_range(0x204, 0x205));
+            let counts: Vec<&str> = lines.iter().map(|line| &line[..15]).collect();
+            assert_eq!(
+                counts,
+                ["0000000003 0204", "0000000005 0204", "0000000007 0204"]
+            );
+        }
+
+        #[test]
+        fn limit_stops_the_trace() {
+            let lines = trace(&PROGRAM, 5, |trace| trace.set_limit(Some(2)));
+            assert_eq!(lines.len(), 2);
+            assert!(lines[1].starts_with("0000000002 0202"));
+        }
+    }
+}
@@ -1,11 +1,102 @@
 pub mod window_driver {
-    use crate::chip8::processor::chip::Chip8;
-    use sdl2::{event::Event, keyboard::Keycode, render::Canvas, video::Window, EventPump, Sdl};
+    use crate::chip8::{
+        keymap::key_bindings::{HostKey, Keymap, KEYPAD_LAYOUT},
+        platform::variant::Platform,
+        processor::chip::{Chip8, CHIP8_FONTSET, HEIGHT, WIDTH},
+        state::save_state::{SaveSlots, SLOTS},
+        theme::colour_theme::Theme,
+    };
+    use crate::emulator_driver::DEFAULT_SCALE;
+    use sdl2::{
+        controller::GameController,
+        event::Event,
+        event::WindowEvent,
+        keyboard::{Keycode, Mod, Scancode},
+        mouse::MouseButton,
+        pixels::Color,
+        rect::Rect,
+        render::Canvas,
+        video::{FullscreenType, Window},
+        EventPump, GameControllerSubsystem, Sdl,
+    };
+    use std::fs;
+
+    const TITLE: &str = "Chip8 Emulator";
+
+    // CHIP-8X background colours: blue, black, green and red
+    const CHIP8X_BACKGROUNDS: [Color; 4] = [
+        Color::RGB(0x00, 0x00, 0x80),
+        Color::RGB(0x00, 0x00, 0x00),
+        Color::RGB(0x00, 0x80, 0x00),
+        Color::RGB(0x80, 0x00, 0x00),
+    ];
+
+    // CHIP-8X foreground colours: black, red, blue, violet, green, yellow, aqua and white
+    const CHIP8X_FOREGROUNDS: [Color; 8] = [
+        Color::RGB(0x00, 0x00, 0x00),
+        Color::RGB(0xFF, 0x00, 0x00),
+        Color::RGB(0x00, 0x00, 0xFF),
+        Color::RGB(0xFF, 0x00, 0xFF),
+        Color::RGB(0x00, 0xFF, 0x00),
+        Color::RGB(0xFF, 0xFF, 0x00),
+        Color::RGB(0x00, 0xFF, 0xFF),
+        Color::RGB(0xFF, 0xFF, 0xFF),
+    ];
+
+    // Save slot hotkeys, F1 to F9 load slots 1 to 9 and with shift held save them
+    const SLOT_KEYS: [Keycode; SLOTS as usize] = [
+        Keycode::F1,
+        Keycode::F2,
+        Keycode::F3,
+        Keycode::F4,
+        Keycode::F5,
+        Keycode::F6,
+        Keycode::F7,
+        Keycode::F8,
+        Keycode::F9,
+    ];
+
+    // Held to play execution backwards
+    const REWIND_KEY: Keycode = Keycode::Backspace;
+
+    // Switches to the next built-in colour theme
+    const THEME_KEY: Keycode = Keycode::F10;
+
+    // Switches between a window and desktop fullscreen
+    const FULLSCREEN_KEY: Keycode = Keycode::F11;
+
+    // Face of a key on the on-screen keypad that is not held down
+    const KEYPAD_KEY_COLOUR: Color = Color::RGB(0x40, 0x40, 0x40);
+
+    // Mouse events SDL synthesizes from touches carry this id, SDL_TOUCH_MOUSEID, the touches
+    // are handled as finger events instead
+    const TOUCH_MOUSE_ID: u32 = u32::MAX;
 
     pub struct Win {
         event_pump: EventPump,
         canvas: Canvas<Window>,
         running: bool,
+        // Background and plane colours as 0xRRGGBB
+        palette: [u32; 4],
+        // Draws the next frame even if the display has not changed
+        redraw: bool,
+        // Scales the display by whole numbers only, leaving wider bars around it
+        integer_scaling: bool,
+        save_slots: Option<SaveSlots>,
+        // Whether the slot hotkeys may load states, saving is always allowed
+        slot_loading: bool,
+        rewinding: bool,
+        keymap: Keymap,
+        game_controller: GameControllerSubsystem,
+        // Open controllers, dropping one closes it
+        controllers: Vec<GameController>,
+        keypad: bool,
+        // Key held by the mouse on the on-screen keypad
+        clicked: Option<u8>,
+        // Keys held by fingers on the on-screen keypad, by finger id
+        touches: Vec<(i64, u8)>,
+        // Keypad state the on-screen keypad was last drawn with
+        drawn_keys: [bool; 16],
     }
 
     impl Win {
@@ -13,41 +104,455 @@ pub mod window_driver {
             self.running
         }
 
+        /// Whether the rewind key is held
+        pub const fn is_rewinding(&self) -> bool {
+            self.rewinding
+        }
+
         pub fn handle_events(&mut self, chip8: &mut Chip8) {
-            for event in self.event_pump.poll_iter() {
+            let events: Vec<Event> = self.event_pump.poll_iter().collect();
+            for event in events {
                 match event {
                     Event::Quit { .. }
                     | Event::KeyDown {
                         keycode: Some(Keycode::Escape),
                         ..
                     } => self.running = false,
-                    Event::KeyDown { keycode: code, .. } => {
-                        chip8.set_action(code.unwrap(), 1);
+                    Event::KeyDown {
+                        keycode: Some(REWIND_KEY),
+                        ..
+                    } => self.rewinding = true,
+                    Event::KeyUp {
+                        keycode: Some(REWIND_KEY),
+                        ..
+                    } => self.rewinding = false,
+                    Event::KeyDown {
+                        keycode: Some(THEME_KEY),
+                        repeat: false,
+                        ..
+                    } => {
+                        let theme = Theme::after(self.palette);
+                        println!("Theme: {}", theme.name);
+                        self.set_palette(theme.palette);
+                    }
+                    Event::KeyDown {
+                        keycode: Some(FULLSCREEN_KEY),
+                        repeat: false,
+                        ..
+                    } => {
+                        let fullscreen =
+                            self.canvas.window().fullscreen_state() == FullscreenType::Off;
+                        if let Err(e) = self.set_fullscreen(fullscreen) {
+                            eprintln!("Could not switch fullscreen: {}", e);
+                        }
+                    }
+                    Event::Window {
+                        win_event: WindowEvent::SizeChanged(..) | WindowEvent::Exposed,
+                        ..
+                    } => self.redraw = true,
+                    Event::KeyDown {
+                        keycode: Some(code),
+                        keymod,
+                        repeat: false,
+                        ..
+                    } if SLOT_KEYS.contains(&code) => {
+                        let slot = SLOT_KEYS.iter().position(|&key| key == code).unwrap() as u8 + 1;
+                        if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                            self.save_state(chip8, slot);
+                        } else {
+                            self.load_state(chip8, slot);
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode, scancode, ..
+                    } => self.set_key(chip8, keycode, scancode, true),
+                    Event::KeyUp {
+                        keycode, scancode, ..
+                    } => self.set_key(chip8, keycode, scancode, false),
+                    Event::ControllerDeviceAdded { which, .. } => self.open_controller(which),
+                    Event::ControllerDeviceRemoved { which, .. } => {
+                        if let Some(i) = self
+                            .controllers
+                            .iter()
+                            .position(|c| c.instance_id() == which)
+                        {
+                            let controller = self.controllers.remove(i);
+                            println!("Controller disconnected: {}", controller.name());
+                        }
+                    }
+                    Event::ControllerButtonDown { button, .. } => {
+                        self.set_keys(chip8, &[HostKey::Button(button)], true)
+                    }
+                    Event::ControllerButtonUp { button, .. } => {
+                        self.set_keys(chip8, &[HostKey::Button(button)], false)
+                    }
+                    Event::MouseButtonDown {
+                        which,
+                        mouse_btn: MouseButton::Left,
+                        x,
+                        y,
+                        ..
+                    } if which != TOUCH_MOUSE_ID => {
+                        let (x, y) = self.window_to_output(x, y);
+                        self.clicked = self.keypad_key_at(x, y);
+                        if let Some(key) = self.clicked {
+                            chip8.set_action(key, true);
+                        }
+                    }
+                    Event::MouseButtonUp {
+                        mouse_btn: MouseButton::Left,
+                        ..
+                    } => {
+                        if let Some(key) = self.clicked.take() {
+                            chip8.set_action(key, false);
+                        }
                     }
-                    Event::KeyUp { keycode: code, .. } => {
-                        chip8.set_action(code.unwrap(), 0);
+                    Event::FingerDown {
+                        finger_id, x, y, ..
+                    } => {
+                        // Finger positions are fractions of the window size
+                        let (width, height) = self.output_size();
+                        let (x, y) = ((x * width as f32) as i32, (y * height as f32) as i32);
+                        if let Some(key) = self.keypad_key_at(x, y) {
+                            chip8.set_action(key, true);
+                            self.touches.push((finger_id, key));
+                        }
+                    }
+                    Event::FingerUp { finger_id, .. } => {
+                        if let Some(i) = self.touches.iter().position(|&(id, _)| id == finger_id) {
+                            let (_, key) = self.touches.remove(i);
+                            chip8.set_action(key, false);
+                        }
                     }
                     _ => {}
                 }
             }
         }
 
+        /// Replaces the default keyboard and controller bindings of the hex keys
+        pub fn set_keymap(&mut self, keymap: Keymap) {
+            self.keymap = keymap;
+        }
+
+        fn set_key(
+            &self,
+            chip8: &mut Chip8,
+            keycode: Option<Keycode>,
+            scancode: Option<Scancode>,
+            pressed: bool,
+        ) {
+            // A key: binding of the symbol replaces the binding of the key position, so on
+            // AZERTY 4 = key:Q does not also press 7, bound to the position Q sits at
+            let keycode = keycode
+                .map(HostKey::Keycode)
+                .filter(|&host_key| self.keymap.lookup(host_key).next().is_some());
+            if let Some(host_key) = keycode.or(scancode.map(HostKey::Scancode)) {
+                self.set_keys(chip8, &[host_key], pressed);
+            }
+        }
+
+        // Keyboard keys and controller buttons feed the same keypad
+        fn set_keys(&self, chip8: &mut Chip8, host_keys: &[HostKey], pressed: bool) {
+            for &host_key in host_keys {
+                for key in self.keymap.lookup(host_key) {
+                    chip8.set_action(key, pressed);
+                }
+            }
+        }
+
+        // Controllers plugged in before the window opened are announced as added too
+        fn open_controller(&mut self, joystick_index: u32) {
+            match self.game_controller.open(joystick_index) {
+                Ok(controller) => {
+                    println!("Controller connected: {}", controller.name());
+                    self.controllers.push(controller);
+                }
+                Err(e) => eprintln!("Could not open controller {}: {}", joystick_index, e),
+            }
+        }
+
+        /// Enables the save slot hotkeys, storing states in `save_slots`
+        pub fn set_save_slots(&mut self, save_slots: SaveSlots) {
+            self.save_slots = Some(save_slots);
+        }
+
+        /// Allows or ignores loading states with the slot hotkeys
+        pub fn set_slot_loading(&mut self, enabled: bool) {
+            self.slot_loading = enabled;
+        }
+
+        fn save_state(&self, chip8: &Chip8, slot: u8) {
+            if let Some(save_slots) = &self.save_slots {
+                let path = save_slots.path(slot);
+                match fs::write(&path, chip8.save_state()) {
+                    Ok(()) => println!("Saved state to slot {}", slot),
+                    Err(e) => eprintln!("Could not save {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        fn load_state(&self, chip8: &mut Chip8, slot: u8) {
+            if !self.slot_loading {
+                println!("Loading a state is disabled while a movie records or replays");
+                return;
+            }
+            if let Some(save_slots) = &self.save_slots {
+                let path = save_slots.path(slot);
+                let loaded = fs::read(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|state| chip8.load_state(&state).map_err(|e| e.to_string()));
+                match loaded {
+                    Ok(()) => println!("Loaded state from slot {}", slot),
+                    Err(e) => eprintln!("Could not load {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        /// Replaces the background and plane colours, given as `0xRRGGBB`
+        pub fn set_palette(&mut self, palette: [u32; 4]) {
+            self.palette = palette;
+            self.redraw = true;
+        }
+
+        /// Shows the title of the running ROM in the title bar
+        pub fn set_title(&mut self, rom_title: &str) -> Result<(), String> {
+            self.canvas
+                .window_mut()
+                .set_title(&format!("{} - {}", TITLE, rom_title))
+                .map_err(|e| e.to_string())
+        }
+
+        /// Resizes the window to show a display of `resolution` with `scale` window pixels per
+        /// CHIP-8 pixel, next to the keypad when it is shown
+        pub fn set_scale(&mut self, scale: u32, resolution: (usize, usize)) -> Result<(), String> {
+            let (width, height) = (resolution.0 as u32 * scale, resolution.1 as u32 * scale);
+            let keypad_width = if self.keypad { height } else { 0 };
+            self.canvas
+                .window_mut()
+                .set_size(width + keypad_width, height)
+                .map_err(|e| e.to_string())?;
+            self.redraw = true;
+            Ok(())
+        }
+
+        /// Limits the display to whole multiples of its resolution, so every CHIP-8 pixel covers
+        /// the same number of screen pixels
+        pub fn set_integer_scaling(&mut self, integer_scaling: bool) {
+            self.integer_scaling = integer_scaling;
+            self.redraw = true;
+        }
+
+        /// Switches between the window and desktop fullscreen
+        pub fn set_fullscreen(&mut self, fullscreen: bool) -> Result<(), String> {
+            self.canvas.window_mut().set_fullscreen(if fullscreen {
+                FullscreenType::Desktop
+            } else {
+                FullscreenType::Off
+            })?;
+            self.redraw = true;
+            Ok(())
+        }
+
+        // Size of the drawing area in pixels, larger than the window size on high-DPI screens
+        fn output_size(&self) -> (u32, u32) {
+            self.canvas
+                .output_size()
+                .unwrap_or_else(|_| self.canvas.window().size())
+        }
+
+        // Converts a point in window coordinates, such as the mouse position, to output pixels
+        fn window_to_output(&self, x: i32, y: i32) -> (i32, i32) {
+            let (window_width, window_height) = self.canvas.window().size();
+            let (width, height) = self.output_size();
+            (
+                (x as i64 * width as i64 / window_width.max(1) as i64) as i32,
+                (y as i64 * height as i64 / window_height.max(1) as i64) as i32,
+            )
+        }
+
+        /// Widens the window to show a clickable hex keypad to the right of the display
+        pub fn show_keypad(&mut self) -> Result<(), String> {
+            if !self.keypad {
+                let (width, height) = self.canvas.window().size();
+                self.canvas
+                    .window_mut()
+                    .set_size(width + height, height)
+                    .map_err(|e| e.to_string())?;
+                self.keypad = true;
+            }
+            Ok(())
+        }
+
+        // The square the keypad is drawn in on the right edge of the window, taking at most
+        // half its width
+        fn keypad_rect(&self) -> Option<Rect> {
+            let (width, height) = self.output_size();
+            let side = height.min(width / 2);
+            self.keypad.then(|| {
+                Rect::new(
+                    (width - side) as i32,
+                    ((height - side) / 2) as i32,
+                    side,
+                    side,
+                )
+            })
+        }
+
+        // The area the display is drawn in, the largest that fits beside the keypad with square
+        // pixels, centred with bars around it. Returns the area and the size of a pixel
+        fn display_rect(&self, resolution: (usize, usize)) -> (Rect, f32) {
+            let (width, height) = self.output_size();
+            let width = width - self.keypad_rect().map_or(0, |rect| rect.width());
+            let (columns, rows) = (resolution.0 as f32, resolution.1 as f32);
+
+            let mut scale = (width as f32 / columns).min(height as f32 / rows);
+            if self.integer_scaling && scale >= 1.0 {
+                scale = scale.floor();
+            }
+
+            let (display_width, display_height) = (columns * scale, rows * scale);
+            let rect = Rect::new(
+                ((width as f32 - display_width) / 2.0) as i32,
+                ((height as f32 - display_height) / 2.0) as i32,
+                display_width as u32,
+                display_height as u32,
+            );
+            (rect, scale)
+        }
+
+        // The hex key on the keypad under a point of the window
+        fn keypad_key_at(&self, x: i32, y: i32) -> Option<u8> {
+            let rect = self.keypad_rect()?;
+            if !rect.contains_point((x, y)) {
+                return None;
+            }
+            let cell = (rect.height() / 4).max(1) as i32;
+            let (column, row) = ((x - rect.x()) / cell, (y - rect.y()) / cell);
+            KEYPAD_LAYOUT
+                .get((row * 4 + column.min(3)) as usize)
+                .copied()
+        }
+
         pub fn draw(&mut self, chip8: &mut Chip8) {
-            if chip8.should_draw() {
-                self.canvas.clear();
-                chip8.update_quads(&mut self.canvas);
+            let keys = std::array::from_fn(|key| chip8.is_key_pressed(key as u8));
+            let keys_changed = self.keypad && keys != self.drawn_keys;
+
+            if chip8.should_draw() || keys_changed || self.redraw {
+                self.redraw = false;
+                self.update_quads(chip8);
+                self.draw_keypad(&keys);
+                self.drawn_keys = keys;
                 chip8.draw_done();
                 self.canvas.present();
             }
         }
 
-        pub fn new(sdl_context: &Sdl) -> Result<Win, String> {
-            let window = sdl_context
+        // Draws the 4x4 keypad with the built-in font, held keys lit in the first plane colour
+        fn draw_keypad(&mut self, keys: &[bool; 16]) {
+            let rect = match self.keypad_rect() {
+                Some(rect) => rect,
+                None => return,
+            };
+            let cell = rect.height() / 4;
+            let margin = cell / 16;
+            // Digits are 4x5 sprites, scaled to half the height of a key
+            let scale = (cell / 10).max(1);
+
+            for (i, &key) in KEYPAD_LAYOUT.iter().enumerate() {
+                let x = rect.x() + (i as u32 % 4 * cell) as i32;
+                let y = rect.y() + (i as u32 / 4 * cell) as i32;
+                let pressed = keys[key as usize];
+
+                self.canvas.set_draw_color(if pressed {
+                    rgb(self.palette[1])
+                } else {
+                    KEYPAD_KEY_COLOUR
+                });
+                let _key = self.canvas.fill_rect(Rect::new(
+                    x + margin as i32,
+                    y + margin as i32,
+                    cell - 2 * margin,
+                    cell - 2 * margin,
+                ));
+
+                self.canvas.set_draw_color(rgb(if pressed {
+                    self.palette[0]
+                } else {
+                    self.palette[1]
+                }));
+                let left = x + ((cell - 4 * scale) / 2) as i32;
+                let top = y + ((cell - 5 * scale) / 2) as i32;
+                let glyph = &CHIP8_FONTSET[key as usize * 5..key as usize * 5 + 5];
+                for (row, bits) in glyph.iter().enumerate() {
+                    for column in (0..4).filter(|column| bits & (0x80 >> column) != 0) {
+                        let _pixel = self.canvas.fill_rect(Rect::new(
+                            left + (column * scale) as i32,
+                            top + (row as u32 * scale) as i32,
+                            scale,
+                            scale,
+                        ));
+                    }
+                }
+            }
+        }
+
+        fn update_quads(&mut self, chip8: &Chip8) {
+            // Bars around the display are black
+            self.canvas.set_draw_color(Color::BLACK);
+            self.canvas.clear();
+
+            let chip8x = chip8.platform() == Platform::Chip8X;
+            self.canvas.set_draw_color(if chip8x {
+                CHIP8X_BACKGROUNDS[chip8.background_colour() as usize]
+            } else {
+                rgb(self.palette[0])
+            });
+
+            // Scale the active resolution to fit the window
+            let width = chip8.width();
+            let (display, scale) = self.display_rect((width, chip8.height()));
+            let _background = self.canvas.fill_rect(display);
+
+            // Pixel edges are rounded so fractional scales leave no gaps between pixels
+            let edge = |offset: i32, i: usize| offset + (i as f32 * scale).round() as i32;
+
+            // Each pixel holds one bit per plane, selecting its colour from the palette, or on
+            // CHIP-8X the colour of the zone it lies in
+            for (i, &pixel) in chip8.gfx().iter().enumerate().filter(|(_, &p)| p != 0) {
+                let (x, y) = (i % width, i / width);
+                self.canvas.set_draw_color(if chip8x {
+                    CHIP8X_FOREGROUNDS[chip8.zone_colour(x, y) as usize]
+                } else {
+                    rgb(self.palette[pixel as usize % self.palette.len()])
+                });
+                let (left, top) = (edge(display.x(), x), edge(display.y(), y));
+                let _pixel = self.canvas.fill_rect(Rect::new(
+                    left,
+                    top,
+                    (edge(display.x(), x + 1) - left) as u32,
+                    (edge(display.y(), y + 1) - top) as u32,
+                ));
+            }
+        }
+
+        /// Creates the window, `palette` holds the background colour followed by the colours of
+        /// plane 1, plane 2 and both planes as `0xRRGGBB`
+        pub fn new(sdl_context: &Sdl, palette: [u32; 4]) -> Result<Win, String> {
+            let mut window = sdl_context
                 .video()?
-                .window("Chip8 Emulator", 64 * 20, 32 * 20)
+                .window(
+                    TITLE,
+                    WIDTH as u32 * DEFAULT_SCALE,
+                    HEIGHT as u32 * DEFAULT_SCALE,
+                )
                 .position_centered()
+                .resizable()
+                .allow_highdpi()
                 .build()
                 .map_err(|e| e.to_string())?;
+            window
+                .set_minimum_size(WIDTH as u32, HEIGHT as u32)
+                .map_err(|e| e.to_string())?;
 
             let canvas = window
                 .into_canvas()
@@ -60,7 +565,24 @@ pub mod window_driver {
                 event_pump: sdl_context.event_pump()?,
                 canvas,
                 running: true,
+                palette,
+                redraw: false,
+                integer_scaling: false,
+                save_slots: None,
+                slot_loading: true,
+                rewinding: false,
+                keymap: Keymap::default(),
+                game_controller: sdl_context.game_controller()?,
+                controllers: Vec::new(),
+                keypad: false,
+                clicked: None,
+                touches: Vec::new(),
+                drawn_keys: [false; 16],
             })
         }
     }
+
+    fn rgb(colour: u32) -> Color {
+        Color::RGB((colour >> 16) as u8, (colour >> 8) as u8, colour as u8)
+    }
 }
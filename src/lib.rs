@@ -0,0 +1,499 @@
+pub mod chip8;
+
+pub use chip8::{
+    assembler::assembly::{assemble, assemble_at, AsmError},
+    database::rom_database::{rom_hash, Database, RomSettings},
+    debugger::debug_console::Debugger,
+    disassembler::disassembly::{disassemble, disassemble_range, disassemble_rom, Line, Syntax},
+    emulator::core::{ips_to_cycles_per_frame, Emulator, DEFAULT_CYCLES_PER_FRAME, TIMER_HZ},
+    error::chip_error::Chip8Error,
+    gdb::gdb_stub::GdbServer,
+    headless::headless_runner::{run_headless, Halt, KeyPress, DEFAULT_KEY_FRAMES},
+    movie::movie_file::Movie,
+    platform::variant::Platform,
+    processor::chip::{
+        Chip8, Writes, HEIGHT, HIRES_HEIGHT, HIRES_WIDTH, MEMORY_SIZE, PLANES, PROGRAM_START,
+        WIDTH, ZONE_WIDTH,
+    },
+    quirks::quirk_profile::Quirks,
+    random::random_source::{RandomSource, ScriptedRandom, SeededRandom},
+    rewind::rewind_buffer::{Rewind, DEFAULT_REWIND_FRAMES, DEFAULT_REWIND_MEMORY},
+    screen::screen_image::Screen,
+    state::save_state::SaveSlots,
+    theme::colour_theme::Theme,
+    trace::instruction_trace::Trace,
+};
+
+#[cfg(feature = "sdl")]
+pub use chip8::keymap::key_bindings::{HostKey, Keymap};
+
+pub mod emulator_driver {
+    use crate::{
+        assemble_at, Chip8Error, Database, Platform, Quirks, RomSettings, Theme,
+        DEFAULT_REWIND_FRAMES, DEFAULT_REWIND_MEMORY,
+    };
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+    #[cfg(feature = "sdl")]
+    use {
+        crate::chip8::{audio::audio_driver::Audio, window::window_driver::Win},
+        crate::{
+            Debugger, Emulator, GdbServer, Keymap, Movie, Rewind, SaveSlots, Trace,
+            DEFAULT_CYCLES_PER_FRAME, TIMER_HZ,
+        },
+        std::{
+            io::{self, BufRead, Write},
+            sync::mpsc::{self, Receiver},
+            thread,
+            time::{Duration, Instant},
+        },
+    };
+
+    pub const NAME: &str = "CHIP 8";
+
+    /// Extensions of assembly source files, which are assembled when loaded
+    pub const SOURCE_EXTENSIONS: [&str; 2] = ["asm", "s"];
+
+    /// Extension of keymap files picked up beside the ROM they are named after
+    pub const KEYMAP_EXTENSION: &str = "keymap";
+
+    /// Window pixels per CHIP-8 pixel the window opens with
+    pub const DEFAULT_SCALE: u32 = 20;
+
+    // Frames the emulation may fall behind real time before it gives up catching up
+    #[cfg(feature = "sdl")]
+    const MAX_FRAMES_BEHIND: u32 = 5;
+
+    /// Background, plane 1, plane 2 and both planes colours as `0xRRGGBB`
+    pub const DEFAULT_PALETTE: [u32; 4] = Theme::CLASSIC.palette;
+
+    /// Run settings. The CPU speed, quirks, platform and palette fall back on the ROM database
+    /// when `None`, then on their defaults
+    #[derive(Clone)]
+    pub struct Options {
+        pub cycles_per_frame: Option<usize>,
+        pub quirks: Option<Quirks>,
+        pub platform: Option<Platform>,
+        pub palette: Option<[u32; 4]>,
+        /// Databases in the chip-8-database format searched before the bundled one
+        pub databases: Vec<PathBuf>,
+        /// Directory save states are stored in, next to the ROM when `None`
+        pub state_dir: Option<PathBuf>,
+        /// Frames of history kept for rewinding, 0 disables rewinding
+        pub rewind_frames: usize,
+        /// Cap on the memory used by the rewind history, in bytes
+        pub rewind_memory: usize,
+        /// Seed of the random numbers, drawn from the operating system when not set
+        pub seed: Option<u64>,
+        /// Starts paused in the debugger, taking commands from stdin
+        pub debug: bool,
+        /// Port on localhost to serve the GDB remote serial protocol on
+        pub gdb_port: Option<u16>,
+        /// File every executed instruction is traced to, `-` for stdout
+        pub trace: Option<PathBuf>,
+        /// Addresses traced, from the first to the second inclusive
+        pub trace_range: Option<(u16, u16)>,
+        /// Instructions traced before the trace stops
+        pub trace_limit: Option<u64>,
+        /// Movie file the run is recorded to
+        pub record: Option<PathBuf>,
+        /// Movie file replayed and verified instead of taking keyboard input
+        pub replay: Option<PathBuf>,
+        /// Keymap file rebinding the hex keys to keyboard keys and controller buttons, see
+        /// `Keymap`. When `None` a keymap beside the ROM named after it is used if there is one
+        pub keymap: Option<PathBuf>,
+        /// Prints the active key bindings before starting
+        pub show_keymap: bool,
+        /// Shows a hex keypad next to the display that can be clicked or touched
+        pub keypad: bool,
+        /// Window pixels per CHIP-8 pixel the window opens with
+        pub scale: u32,
+        /// Scales the display by whole numbers only
+        pub integer_scaling: bool,
+        /// Starts in desktop fullscreen
+        pub fullscreen: bool,
+    }
+
+    impl Default for Options {
+        fn default() -> Self {
+            Options {
+                cycles_per_frame: None,
+                quirks: None,
+                platform: None,
+                palette: None,
+                databases: Vec::new(),
+                state_dir: None,
+                rewind_frames: DEFAULT_REWIND_FRAMES,
+                rewind_memory: DEFAULT_REWIND_MEMORY,
+                seed: None,
+                debug: false,
+                gdb_port: None,
+                trace: None,
+                trace_range: None,
+                trace_limit: None,
+                record: None,
+                replay: None,
+                keymap: None,
+                show_keymap: false,
+                keypad: false,
+                scale: DEFAULT_SCALE,
+                integer_scaling: false,
+                fullscreen: false,
+            }
+        }
+    }
+
+    impl Options {
+        /// Fills in the settings left unset with the ones the ROM database holds
+        pub fn apply(&mut self, settings: &RomSettings) {
+            self.cycles_per_frame = self.cycles_per_frame.or(settings.cycles_per_frame);
+            self.quirks = self.quirks.or(settings.quirks);
+            self.platform = self.platform.or(settings.platform);
+            if self.palette.is_none() && !settings.colours.is_empty() {
+                self.palette = Some(settings.palette(DEFAULT_PALETTE));
+            }
+        }
+    }
+
+    /// Looks a ROM up by its SHA-1 in `databases`, then in the bundled database
+    pub fn lookup_rom(rom: &[u8], databases: &[PathBuf]) -> Result<Option<RomSettings>, String> {
+        let mut database = Database::bundled();
+        for path in databases {
+            let json = fs::read_to_string(path)
+                .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+            database
+                .extend_from_json(&json)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        Ok(database.lookup(rom).cloned())
+    }
+
+    /// Parses a comma separated list of up to 4 hex `RRGGBB` colours, colours that are left
+    /// out keep their default
+    pub fn parse_palette(colours: &str) -> Result<[u32; 4], String> {
+        let mut palette = DEFAULT_PALETTE;
+        let colours: Vec<&str> = colours.split(',').collect();
+
+        if colours.len() > palette.len() {
+            return Err(format!("A palette has at most {} colours", palette.len()));
+        }
+
+        for (slot, colour) in palette.iter_mut().zip(colours) {
+            let hex = colour.trim().trim_start_matches('#');
+            *slot = u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| hex.len() == 6)
+                .ok_or_else(|| format!("Invalid colour \"{}\", expected RRGGBB", colour))?;
+        }
+
+        Ok(palette)
+    }
+
+    /// Reads a ROM, assembling it at the program start address of `platform` when the file is
+    /// assembly source, ending in `.asm` or `.s`
+    pub fn load_program(path: &str, platform: Platform) -> Result<Vec<u8>, String> {
+        if !is_source(path) {
+            return fs::read(path).map_err(|e| Chip8Error::from(e).to_string());
+        }
+
+        let source =
+            fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        assemble_at(&source, platform.program_start()).map_err(|e| format!("{}:{}", path, e))
+    }
+
+    fn is_source(path: &str) -> bool {
+        Path::new(path)
+            .extension()
+            .is_some_and(|extension| SOURCE_EXTENSIONS.iter().any(|&e| extension == e))
+    }
+
+    /// Loads the program at `path` and fills the options left unset from the ROM database.
+    /// Source is assembled again when the database moves it to a platform starting elsewhere
+    pub fn load_with_settings(
+        path: &str,
+        options: &mut Options,
+    ) -> Result<(Vec<u8>, Option<RomSettings>), String> {
+        let assembled_for = options.platform.unwrap_or_default();
+        let mut rom = load_program(path, assembled_for)?;
+
+        let settings = lookup_rom(&rom, &options.databases)?;
+        if let Some(settings) = &settings {
+            options.apply(settings);
+        }
+
+        let platform = options.platform.unwrap_or_default();
+        if is_source(path) && platform.program_start() != assembled_for.program_start() {
+            rom = load_program(path, platform)?;
+        }
+
+        Ok((rom, settings))
+    }
+
+    #[cfg(feature = "sdl")]
+    pub fn start(rom_path: &str, options: &Options) -> Result<(), String> {
+        let mut options = options.clone();
+        let (rom, settings) = load_with_settings(rom_path, &mut options)?;
+        if let Some(settings) = &settings {
+            println!("Recognised {}", settings.title);
+        }
+
+        let sdl_context = sdl2::init()?;
+        let mut audio_device = Audio::new(&sdl_context.audio()?);
+        let mut window = Win::new(&sdl_context, options.palette.unwrap_or(DEFAULT_PALETTE))?;
+        if let Some(settings) = &settings {
+            window.set_title(&settings.title)?;
+        }
+        window.set_save_slots(match &options.state_dir {
+            Some(dir) => SaveSlots::in_dir(dir, Path::new(rom_path)),
+            None => SaveSlots::beside(Path::new(rom_path)),
+        });
+
+        // Without --keymap a keymap named after the ROM, such as game.ch8.keymap, is used
+        let rom_keymap = PathBuf::from(format!("{}.{}", rom_path, KEYMAP_EXTENSION));
+        let keymap_path = options
+            .keymap
+            .as_ref()
+            .or_else(|| Some(&rom_keymap).filter(|path| path.is_file()));
+        let keymap = match (keymap_path, &settings) {
+            (Some(path), _) => fs::read_to_string(path)
+                .map_err(|e| format!("Could not read {}: {}", path.display(), e))?
+                .parse()
+                .map_err(|e| format!("{}: {}", path.display(), e))?,
+            // Controllers follow the keys the database says the ROM uses
+            (None, Some(settings)) => {
+                let mut keymap = Keymap::default();
+                keymap.apply_hints(&settings.keys);
+                keymap
+            }
+            (None, None) => Keymap::default(),
+        };
+        if options.show_keymap {
+            println!("{}", keymap.legend());
+        }
+        window.set_keymap(keymap);
+        if options.keypad {
+            window.show_keypad()?;
+        }
+
+        let mut emulator = Emulator::with_platform(
+            options.quirks.unwrap_or_default(),
+            options.platform.unwrap_or_default(),
+        );
+        emulator.set_cycles_per_frame(options.cycles_per_frame.unwrap_or(DEFAULT_CYCLES_PER_FRAME));
+        if let Some(seed) = options.seed {
+            emulator.set_seed(seed);
+        }
+
+        // Size the window for the resolution the platform starts in
+        window.set_scale(options.scale, emulator.resolution())?;
+        window.set_integer_scaling(options.integer_scaling);
+        if options.fullscreen {
+            window.set_fullscreen(true)?;
+        }
+
+        if let Some(path) = &options.replay {
+            let movie: Movie = fs::read_to_string(path)
+                .map_err(|e| format!("Could not read {}: {}", path.display(), e))?
+                .parse()?;
+            emulator.replay(movie, &rom).map_err(|e| e.to_string())?;
+        } else if options.record.is_some() {
+            emulator.record(&rom).map_err(|e| e.to_string())?;
+        } else {
+            emulator.load_rom(&rom).map_err(|e| e.to_string())?;
+        }
+
+        if let Some(path) = &options.trace {
+            let mut trace = if path.as_os_str() == "-" {
+                Trace::stdout()
+            } else {
+                Trace::to_file(path)
+                    .map_err(|e| format!("Could not create {}: {}", path.display(), e))?
+            };
+            if let Some((start, end)) = options.trace_range {
+                trace.set_range(start, end);
+            }
+            trace.set_limit(options.trace_limit);
+            emulator.set_trace(Some(trace));
+        }
+
+        // Rewinding would break the determinism of movies
+        let rewind_frames = if options.record.is_some() || options.replay.is_some() {
+            0
+        } else {
+            options.rewind_frames
+        };
+        let mut rewind = Rewind::new(rewind_frames, options.rewind_memory);
+
+        let debugger = options.debug.then(Debugger::new);
+        let gdb = match options.gdb_port {
+            Some(port) => {
+                let gdb = GdbServer::bind(port)
+                    .map_err(|e| format!("Could not listen on port {}: {}", port, e))?;
+                println!("Waiting for GDB on localhost:{}", port);
+                Some(gdb)
+            }
+            None => None,
+        };
+        let result = run(
+            &mut window,
+            &mut audio_device,
+            &mut emulator,
+            &mut rewind,
+            debugger,
+            gdb,
+        );
+
+        // Keep the recording even when the run ended in a fault
+        if let (Some(path), Some(movie)) = (&options.record, emulator.stop_recording()) {
+            fs::write(path, movie.to_text())
+                .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+            println!("Recorded {} frames to {}", movie.frames(), path.display());
+        }
+
+        result
+    }
+
+    #[cfg(feature = "sdl")]
+    fn run(
+        window: &mut Win,
+        audio_device: &mut Audio,
+        emulator: &mut Emulator,
+        rewind: &mut Rewind,
+        mut debugger: Option<Debugger>,
+        mut gdb: Option<GdbServer>,
+    ) -> Result<(), String> {
+        let mut replaying = emulator.is_replaying();
+
+        let commands = debugger.as_ref().map(|_| {
+            println!("Paused in the debugger, type help for the commands");
+            prompt();
+            read_commands()
+        });
+
+        let frame_duration = Duration::from_secs(1) / TIMER_HZ as u32;
+        let mut last_frame = Instant::now();
+        let mut lag = Duration::ZERO;
+
+        while window.is_running() {
+            // Loading a state would break the determinism of movies, like rewinding
+            window.set_slot_loading(!emulator.is_recording() && !emulator.is_replaying());
+            window.handle_events(emulator.chip8_mut());
+
+            if let (Some(debugger), Some(commands)) = (&mut debugger, &commands) {
+                for line in commands.try_iter() {
+                    let output = debugger.execute(&line, emulator);
+                    if debugger.has_quit() {
+                        return Ok(());
+                    }
+                    if !output.is_empty() {
+                        println!("{}", output);
+                    }
+                    prompt();
+                }
+            }
+
+            if let Some(gdb) = &mut gdb {
+                gdb.poll(emulator)
+                    .map_err(|e| format!("GDB connection: {}", e))?;
+                if gdb.is_killed() {
+                    return Ok(());
+                }
+            }
+
+            let now = Instant::now();
+            lag = (lag + (now - last_frame)).min(frame_duration * MAX_FRAMES_BEHIND);
+            last_frame = now;
+
+            if lag < frame_duration {
+                thread::sleep(frame_duration - lag);
+                continue;
+            }
+
+            while lag >= frame_duration {
+                if window.is_rewinding() {
+                    // Step back a frame, holding on the oldest one once history runs out
+                    if let Some(state) = rewind.pop() {
+                        emulator.load_state(state).map_err(|e| e.to_string())?;
+                    }
+                } else if let Some(debugger) = &mut debugger {
+                    if let Some(stop) = debugger.run_frame(emulator) {
+                        println!("{}", stop);
+                        prompt();
+                    }
+                } else if let Some(gdb) = &mut gdb {
+                    gdb.run_frame(emulator).map_err(|e| e.to_string())?;
+                } else {
+                    emulator.run_frame().map_err(|e| e.to_string())?;
+                    rewind.push(emulator.save_state());
+                }
+                lag -= frame_duration;
+
+                if replaying && !emulator.is_replaying() {
+                    println!("Replay finished, {} frames verified", emulator.frame());
+                    replaying = false;
+                }
+            }
+
+            audio_device.play(emulator.chip8_mut());
+
+            window.draw(emulator.chip8_mut());
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "sdl")]
+    fn prompt() {
+        print!("(chip8) ");
+        let _ = io::stdout().flush();
+    }
+
+    #[cfg(feature = "sdl")]
+    // Reads debugger commands on a separate thread so the window keeps responding while paused
+    fn read_commands() -> Receiver<String> {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        receiver
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::rom_hash;
+
+        #[test]
+        fn source_is_assembled_for_the_platform_the_database_names() {
+            let dir = std::env::temp_dir().join(format!("chip8-load-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let source = dir.join("loop.asm");
+            fs::write(&source, "loop: JP loop\n").unwrap();
+            // Assembled at 0x200 the loop is 1200, the database says it is a CHIP-8X program
+            let database = dir.join("programs.json");
+            let json = format!(
+                r#"[{{ "title": "Loop", "roms": {{ "{}": {{ "platforms": ["chip8x"] }} }} }}]"#,
+                rom_hash(&[0x12, 0x00])
+            );
+            fs::write(&database, json).unwrap();
+
+            let mut options = Options {
+                databases: vec![database],
+                ..Options::default()
+            };
+            let (rom, settings) =
+                load_with_settings(source.to_str().unwrap(), &mut options).unwrap();
+            fs::remove_dir_all(&dir).unwrap();
+
+            assert_eq!(settings.unwrap().title, "Loop");
+            assert_eq!(options.platform, Some(Platform::Chip8X));
+            assert_eq!(rom, [0x13, 0x00]);
+        }
+    }
+}
@@ -1,51 +1,382 @@
-mod chip8;
+use emulator_chip8::{
+    assemble_at, disassemble_rom,
+    emulator_driver::{load_with_settings, parse_palette, Options, DEFAULT_PALETTE},
+    ips_to_cycles_per_frame, run_headless, Emulator, KeyPress, Platform, Screen, Syntax, Theme,
+    DEFAULT_CYCLES_PER_FRAME,
+};
+use std::{fs, path::Path};
 
-pub mod emulator_driver {
-    use crate::chip8::{
-        audio::audio_driver::Audio, processor::chip::Chip8, window::window_driver::Win,
-    };
-    use std::env;
+const USAGE: &str = "Usage: emulator_chip8 [--ipf N | --ips N] [--quirks legacy|vip|schip|xochip] \
+    [--platform chip8|hires|chip8x|eti660] \
+    [--palette RRGGBB,RRGGBB,RRGGBB,RRGGBB | --theme classic|lcd|amber|contrast] \
+    [--state-dir DIR] [--rewind-frames N] [--rewind-memory MB] \
+    [--seed N] [--record MOVIE | --replay MOVIE] [--debug | --gdb PORT] \
+    [--trace FILE|-] [--trace-range START-END] [--trace-limit N] [--keymap FILE] [--show-keymap] \
+    [--keypad] [--database FILE]... [--scale N] [--integer-scale] [--fullscreen] PATH_TO_ROM
+       emulator_chip8 disasm [--syntax classic|octo] [--platform chip8|hires|chip8x|eti660] \
+    PATH_TO_ROM
+       emulator_chip8 asm [--platform chip8|hires|chip8x|eti660] PATH_TO_SOURCE -o PATH_TO_ROM
+       emulator_chip8 headless [--frames N] [--key FRAME:KEY[:FRAMES]]... [--output FILE|-] \
+    [--reference FILE] [--ipf N | --ips N] [--quirks legacy|vip|schip|xochip] \
+    [--platform chip8|hires|chip8x|eti660] [--palette COLOURS | --theme NAME] \
+    [--database FILE]... [--seed N] PATH_TO_ROM
+
+Source files ending in .asm or .s are assembled before running
+Screens are read and written as PBM for .pbm, PNG for .png and ASCII art otherwise";
+
+// Frames a headless run lasts unless --frames is given, 10 seconds of emulated time
+const DEFAULT_HEADLESS_FRAMES: u64 = 600;
+
+fn parse_number(value: Option<String>, flag: &str) -> Result<usize, String> {
+    value
+        .and_then(|v| v.parse().ok())
+        .filter(|&n| n > 0)
+        .ok_or_else(|| format!("{} requires a positive number", flag))
+}
 
-    pub const NAME: &str = "CHIP 8";
+// Takes an option shared by running in a window and headless, returning whether `arg` is one
+fn parse_run_option(
+    arg: &str,
+    args: &mut impl Iterator<Item = String>,
+    options: &mut Options,
+) -> Result<bool, String> {
+    match arg {
+        "--ipf" => options.cycles_per_frame = Some(parse_number(args.next(), "--ipf")?),
+        "--ips" => {
+            let ips = parse_number(args.next(), "--ips")?;
+            options.cycles_per_frame = Some(ips_to_cycles_per_frame(ips));
+        }
+        "--quirks" => {
+            options.quirks = Some(
+                args.next()
+                    .ok_or_else(|| "--quirks requires a profile name".to_string())?
+                    .parse()?,
+            );
+        }
+        "--platform" => {
+            options.platform = Some(
+                args.next()
+                    .ok_or_else(|| "--platform requires a platform name".to_string())?
+                    .parse()?,
+            );
+        }
+        "--palette" => {
+            options.palette =
+                Some(parse_palette(&args.next().ok_or_else(|| {
+                    "--palette requires a list of colours".to_string()
+                })?)?);
+        }
+        "--theme" => {
+            let theme: Theme = args
+                .next()
+                .ok_or_else(|| "--theme requires a theme name".to_string())?
+                .parse()?;
+            options.palette = Some(theme.palette);
+        }
+        "--database" => {
+            options.databases.push(
+                args.next()
+                    .ok_or_else(|| "--database requires a database file".to_string())?
+                    .into(),
+            );
+        }
+        "--seed" => {
+            options.seed = Some(
+                args.next()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| "--seed requires a number".to_string())?,
+            );
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
 
-    pub fn start(rom_path: Option<&str>) -> Result<(), String> {
-        let sdl_context = sdl2::init()?;
-        let audio_device = Audio::new(&sdl_context.audio()?);
-        let mut window = Win::new(&sdl_context)?;
+// A path argument, rejecting misspelled options rather than taking them for a file name
+fn positional(arg: String) -> Result<String, String> {
+    if arg.starts_with('-') && arg != "-" {
+        return Err(format!("Unknown option \"{}\"\n{}", arg, USAGE));
+    }
+    Ok(arg)
+}
 
-        let mut chip8 = Chip8::new();
+// Prints the disassembly of a ROM
+fn disasm(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut syntax = Syntax::default();
+    let mut platform = Platform::default();
+    let mut rom_path = None;
 
-        if let Some(path) = rom_path {
-            chip8.load(path)?;
-        } else {
-            let args = &mut env::args().collect::<Vec<String>>();
-            if args.len() == 1 {
-                return Err("Please provide a rom to run!".to_string());
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--syntax" => {
+                syntax = args
+                    .next()
+                    .ok_or_else(|| "--syntax requires a syntax".to_string())?
+                    .parse()?;
             }
-            chip8.load(&args[1])?
+            "--platform" => {
+                platform = args
+                    .next()
+                    .ok_or_else(|| "--platform requires a platform name".to_string())?
+                    .parse()?;
+            }
+            _ => rom_path = Some(positional(arg)?),
         }
+    }
+
+    let rom_path = rom_path.ok_or_else(|| format!("Path to rom required!\n{}", USAGE))?;
+    let rom = fs::read(&rom_path).map_err(|e| format!("Could not read {}: {}", rom_path, e))?;
+    for line in disassemble_rom(&rom, platform, syntax) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+// Assembles a source file into a ROM
+fn asm(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut platform = Platform::default();
+    let mut source_path = None;
+    let mut rom_path = None;
 
-        // TODO: move to window.rs
-        while window.is_running() {
-            window.handle_events(&mut chip8);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--platform" => {
+                platform = args
+                    .next()
+                    .ok_or_else(|| "--platform requires a platform name".to_string())?
+                    .parse()?;
+            }
+            "-o" => {
+                rom_path = Some(
+                    args.next()
+                        .ok_or_else(|| "-o requires an output file".to_string())?,
+                );
+            }
+            _ => source_path = Some(positional(arg)?),
+        }
+    }
 
-            chip8.cycle();
+    let source_path = source_path.ok_or_else(|| format!("Path to source required!\n{}", USAGE))?;
+    let rom_path = rom_path.ok_or_else(|| format!("Output path required!\n{}", USAGE))?;
+    let source = fs::read_to_string(&source_path)
+        .map_err(|e| format!("Could not read {}: {}", source_path, e))?;
+    let rom = assemble_at(&source, platform.program_start())
+        .map_err(|e| format!("{}:{}", source_path, e))?;
+    fs::write(&rom_path, &rom).map_err(|e| format!("Could not write {}: {}", rom_path, e))?;
+    println!("Assembled {} bytes to {}", rom.len(), rom_path);
+    Ok(())
+}
 
-            audio_device.play(&mut chip8);
+// Runs a ROM without a window, then writes the final screen and checks it against a reference
+fn headless(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut options = Options::default();
+    let mut frames = DEFAULT_HEADLESS_FRAMES;
+    let mut presses: Vec<KeyPress> = Vec::new();
+    let mut output = None;
+    let mut reference = None;
+    let mut rom_path = None;
 
-            window.draw(&mut chip8);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => frames = parse_number(args.next(), "--frames")? as u64,
+            "--key" => {
+                presses.push(
+                    args.next()
+                        .ok_or_else(|| "--key requires FRAME:KEY[:FRAMES]".to_string())?
+                        .parse()?,
+                );
+            }
+            "--output" => {
+                output = Some(
+                    args.next()
+                        .ok_or_else(|| "--output requires a file or -".to_string())?,
+                );
+            }
+            "--reference" => {
+                reference = Some(
+                    args.next()
+                        .ok_or_else(|| "--reference requires a file".to_string())?,
+                );
+            }
+            _ if parse_run_option(&arg, &mut args, &mut options)? => {}
+            _ => rom_path = Some(positional(arg)?),
         }
+    }
+
+    let rom_path = rom_path.ok_or_else(|| format!("Path to rom required!\n{}", USAGE))?;
+    let (rom, settings) = load_with_settings(&rom_path, &mut options)?;
+    if let Some(settings) = settings {
+        eprintln!("Recognised {}", settings.title);
+    }
+    let palette = options.palette.unwrap_or(DEFAULT_PALETTE);
+
+    let mut emulator = Emulator::with_platform(
+        options.quirks.unwrap_or_default(),
+        options.platform.unwrap_or_default(),
+    );
+    emulator.set_cycles_per_frame(options.cycles_per_frame.unwrap_or(DEFAULT_CYCLES_PER_FRAME));
+    if let Some(seed) = options.seed {
+        emulator.set_seed(seed);
+    }
+    emulator.load_rom(&rom).map_err(|e| e.to_string())?;
+
+    let halt = run_headless(&mut emulator, frames, &presses).map_err(|e| e.to_string())?;
+    eprintln!("Stopped after {} frames: {}", emulator.frame(), halt);
 
-        Ok(())
+    let screen = Screen::capture(emulator.chip8());
+    match output.as_deref() {
+        Some("-") => print!("{}", screen.to_ascii()),
+        Some(path) => screen
+            .save(Path::new(path), palette)
+            .map_err(|e| format!("Could not write {}: {}", path, e))?,
+        None => {}
     }
+
+    if let Some(path) = reference {
+        let expected = Screen::load(Path::new(&path), palette)
+            .map_err(|e| format!("Could not read {}: {}", path, e))?;
+        screen
+            .compare(&expected)
+            .map_err(|e| format!("{}: {}", path, e))?;
+        eprintln!("Screen matches {}", path);
+    }
+    Ok(())
+}
+
+// Parses an inclusive range of hex addresses, START-END
+fn parse_range(range: &str) -> Option<(u16, u16)> {
+    let (start, end) = range.split_once('-')?;
+    let parse = |address: &str| {
+        u16::from_str_radix(
+            address.trim_start_matches("0x").trim_start_matches("0X"),
+            16,
+        )
+        .ok()
+    };
+    Some((parse(start)?, parse(end)?)).filter(|(start, end)| start <= end)
 }
 
 fn main() -> Result<(), String> {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args = std::env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("disasm") => {
+            args.next();
+            return disasm(args);
+        }
+        Some("asm") => {
+            args.next();
+            return asm(args);
+        }
+        Some("headless") => {
+            args.next();
+            return headless(args);
+        }
+        _ => {}
+    }
+    let mut options = Options::default();
+    let mut rom_path = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--state-dir" => {
+                options.state_dir = Some(
+                    args.next()
+                        .ok_or_else(|| "--state-dir requires a directory".to_string())?
+                        .into(),
+                );
+            }
+            "--rewind-frames" => {
+                options.rewind_frames = args
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| "--rewind-frames requires a number".to_string())?;
+            }
+            "--rewind-memory" => {
+                options.rewind_memory = parse_number(args.next(), "--rewind-memory")? * 1024 * 1024;
+            }
+            "--record" => {
+                options.record = Some(
+                    args.next()
+                        .ok_or_else(|| "--record requires a movie file".to_string())?
+                        .into(),
+                );
+            }
+            "--replay" => {
+                options.replay = Some(
+                    args.next()
+                        .ok_or_else(|| "--replay requires a movie file".to_string())?
+                        .into(),
+                );
+            }
+            "--debug" => options.debug = true,
+            "--keymap" => {
+                options.keymap = Some(
+                    args.next()
+                        .ok_or_else(|| "--keymap requires a keymap file".to_string())?
+                        .into(),
+                );
+            }
+            "--show-keymap" => options.show_keymap = true,
+            "--keypad" => options.keypad = true,
+            "--scale" => options.scale = parse_number(args.next(), "--scale")? as u32,
+            "--integer-scale" => options.integer_scaling = true,
+            "--fullscreen" => options.fullscreen = true,
+            "--trace" => {
+                options.trace = Some(
+                    args.next()
+                        .ok_or_else(|| "--trace requires a file or -".to_string())?
+                        .into(),
+                );
+            }
+            "--trace-range" => {
+                options.trace_range = Some(
+                    args.next()
+                        .as_deref()
+                        .and_then(parse_range)
+                        .ok_or_else(|| {
+                            "--trace-range requires hex addresses START-END".to_string()
+                        })?,
+                );
+            }
+            "--trace-limit" => {
+                options.trace_limit = Some(parse_number(args.next(), "--trace-limit")? as u64);
+            }
+            "--gdb" => {
+                options.gdb_port = Some(
+                    args.next()
+                        .and_then(|v| v.parse().ok())
+                        .ok_or_else(|| "--gdb requires a port".to_string())?,
+                );
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if parse_run_option(&arg, &mut args, &mut options)? => {}
+            _ => rom_path = Some(positional(arg)?),
+        }
+    }
+
+    if options.debug && options.gdb_port.is_some() {
+        return Err("--debug and --gdb can not be used together".to_string());
+    }
+    if options.record.is_some() && options.replay.is_some() {
+        return Err("--record and --replay can not be used together".to_string());
+    }
+    // Both can change the machine between frames, which a movie can not reproduce
+    if (options.record.is_some() || options.replay.is_some())
+        && (options.debug || options.gdb_port.is_some())
+    {
+        return Err("--record and --replay can not be used with --debug or --gdb".to_string());
+    }
 
-    if args.len() <= 1 {
-        Err("Path to rom required!".to_string())
-    } else {
-        emulator_driver::start(Some(args.last().unwrap().as_str()))
+    match rom_path {
+        #[cfg(feature = "sdl")]
+        Some(path) => emulator_chip8::emulator_driver::start(&path, &options),
+        #[cfg(not(feature = "sdl"))]
+        Some(_) => {
+            Err("Built without the sdl feature, only disasm, asm and headless can run".to_string())
+        }
+        None => Err(format!("Path to rom required!\n{}", USAGE)),
     }
 }